@@ -0,0 +1,343 @@
+//! Holds primitives for building Uploadcare CDN paths.
+//!
+//! CDN operations are applied to a file by appending them to its UUID,
+//! each operation is prefixed by the "/-/" delimiter, for example:
+//!   d6d34fa9-addd-472c-868d-2e5c105f9fcd/-/resize/x800/-/format/png/
+
+use std::fmt::{self, Display};
+
+/// Default Uploadcare CDN base URL
+pub const CDN_URL: &str = "https://ucarecdn.com";
+
+/// Configuration for building CDN URLs
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Custom CDN domain (CNAME) of the project, e.g. "https://cdn.example.com"
+    /// or just "cdn.example.com" for https. Defaults to `CDN_URL`.
+    pub cdn_base: Option<String>,
+}
+
+/// Builds file delivery URLs with the configured CDN base
+#[derive(Debug, Clone)]
+pub struct UrlBuilder {
+    base: String,
+}
+
+impl UrlBuilder {
+    /// Creates a builder for the CDN base from the config
+    pub fn new(config: &Config) -> Self {
+        let base = match config.cdn_base {
+            Some(ref val) if val.contains("://") => val.trim_end_matches('/').to_string(),
+            Some(ref val) => format!("https://{}", val.trim_end_matches('/')),
+            None => CDN_URL.to_string(),
+        };
+
+        UrlBuilder { base }
+    }
+
+    /// CDN base URL without the trailing slash
+    pub fn base(&self) -> &str {
+        self.base.as_str()
+    }
+
+    /// Returns the delivery URL of the file with operations applied, e.g.
+    /// "https://ucarecdn.com/uuid/-/resize/x800/"
+    pub fn url(&self, uuid: &str, ops: &[Operation]) -> String {
+        let mut url = format!("{}/{}", self.base, with_operations(uuid, ops));
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+
+        url
+    }
+}
+
+impl Default for UrlBuilder {
+    fn default() -> Self {
+        UrlBuilder::new(&Config::default())
+    }
+}
+
+/// CDN media processing operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Resizes an image to fit the given width and/or height
+    Resize {
+        /// Target width in pixels
+        width: Option<u32>,
+        /// Target height in pixels
+        height: Option<u32>,
+    },
+    /// Crops an image to the given dimensions
+    Crop {
+        /// Target width in pixels
+        width: u32,
+        /// Target height in pixels
+        height: u32,
+    },
+    /// Scales an image down and crops it to fill the given dimensions
+    ScaleCrop {
+        /// Target width in pixels
+        width: u32,
+        /// Target height in pixels
+        height: u32,
+    },
+    /// Downscales an image proportionally to fit the given dimensions
+    Preview {
+        /// Target width in pixels
+        width: u32,
+        /// Target height in pixels
+        height: u32,
+    },
+    /// Converts an image to the given format, e.g. "png", see `ImageFormat`
+    /// and `Operation::image_format` for the supported ones
+    Format(String),
+    /// Sets image quality, e.g. "smart" or "lighter"
+    Quality(String),
+    /// Rotates an image counterclockwise by the given angle
+    Rotate(u32),
+    /// Desaturates an image
+    Grayscale,
+    /// Flips an image vertically
+    Flip,
+    /// Mirrors an image horizontally
+    Mirror,
+    /// Any other operation passed as is, e.g. "blur/10"
+    Raw(String),
+}
+
+/// Image formats of the CDN `format` operation. It changes the format the
+/// image is delivered in, the stored file is left as is; use the conversion
+/// API to store derived files.
+///
+/// There is no explicit AVIF, use `Auto` to get AVIF or WebP in browsers
+/// supporting them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// jpeg
+    Jpeg,
+    /// png
+    Png,
+    /// webp
+    Webp,
+    /// auto, the best format supported by the browser requesting the image
+    Auto,
+    /// preserve, keeps the format of the original image
+    Preserve,
+}
+
+impl Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Auto => "auto",
+            ImageFormat::Preserve => "preserve",
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Resize {
+                ref width,
+                ref height,
+            } => write!(
+                f,
+                "resize/{}x{}",
+                width.map(|v| v.to_string()).unwrap_or_default(),
+                height.map(|v| v.to_string()).unwrap_or_default(),
+            ),
+            Operation::Crop { width, height } => write!(f, "crop/{}x{}", width, height),
+            Operation::ScaleCrop { width, height } => {
+                write!(f, "scale_crop/{}x{}", width, height)
+            }
+            Operation::Preview { width, height } => write!(f, "preview/{}x{}", width, height),
+            Operation::Format(ref val) => write!(f, "format/{}", val),
+            Operation::Quality(ref val) => write!(f, "quality/{}", val),
+            Operation::Rotate(angle) => write!(f, "rotate/{}", angle),
+            Operation::Grayscale => write!(f, "grayscale"),
+            Operation::Flip => write!(f, "flip"),
+            Operation::Mirror => write!(f, "mirror"),
+            Operation::Raw(ref val) => write!(f, "{}", val.trim_matches('/')),
+        }
+    }
+}
+
+impl Operation {
+    /// Creates the format operation for delivery of the image in the passed format
+    pub fn image_format(format: ImageFormat) -> Operation {
+        Operation::Format(format.to_string())
+    }
+
+    /// Parses a single operation, e.g. "resize/x800". Operations unknown to the
+    /// library or with malformed arguments are returned as `Raw`.
+    pub fn parse(val: &str) -> Operation {
+        let val = val.trim_matches('/');
+        let mut parts = val.splitn(2, '/');
+        let name = parts.next().unwrap_or("");
+        let arg = parts.next();
+
+        let op = match (name, arg) {
+            ("resize", Some(arg)) => {
+                parse_dimensions(arg).map(|(width, height)| Operation::Resize { width, height })
+            }
+            ("crop", Some(arg)) => {
+                parse_exact_dimensions(arg).map(|(width, height)| Operation::Crop { width, height })
+            }
+            ("scale_crop", Some(arg)) => parse_exact_dimensions(arg)
+                .map(|(width, height)| Operation::ScaleCrop { width, height }),
+            ("preview", Some(arg)) => parse_exact_dimensions(arg)
+                .map(|(width, height)| Operation::Preview { width, height }),
+            ("format", Some(arg)) => Some(Operation::Format(arg.to_string())),
+            ("quality", Some(arg)) => Some(Operation::Quality(arg.to_string())),
+            ("rotate", Some(arg)) => arg.parse().ok().map(Operation::Rotate),
+            ("grayscale", None) => Some(Operation::Grayscale),
+            ("flip", None) => Some(Operation::Flip),
+            ("mirror", None) => Some(Operation::Mirror),
+            _ => None,
+        };
+
+        op.unwrap_or_else(|| Operation::Raw(val.to_string()))
+    }
+}
+
+// parses "WxH" where either of dimensions may be omitted
+fn parse_dimensions(val: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let pos = val.find('x')?;
+    let (width, height) = (&val[..pos], &val[pos + 1..]);
+    let parse = |v: &str| -> Option<Option<u32>> {
+        if v.is_empty() {
+            Some(None)
+        } else {
+            v.parse().ok().map(Some)
+        }
+    };
+
+    Some((parse(width)?, parse(height)?))
+}
+
+// parses "WxH" where both dimensions are required
+fn parse_exact_dimensions(val: &str) -> Option<(u32, u32)> {
+    match parse_dimensions(val)? {
+        (Some(width), Some(height)) => Some((width, height)),
+        _ => None,
+    }
+}
+
+/// Parses operations joined by the "/-/" delimiter, e.g.
+/// "resize/x800/-/format/png/". Returns an empty list for an empty string.
+pub fn parse_operations(val: &str) -> Vec<Operation> {
+    val.trim_start_matches("-/")
+        .split("/-/")
+        .map(|op| op.trim_matches('/'))
+        .filter(|op| !op.is_empty())
+        .map(Operation::parse)
+        .collect()
+}
+
+/// Appends operations to the file UUID, resulting in "uuid/-/op1/-/op2/".
+/// Returns just the UUID if there are no operations.
+pub fn with_operations(uuid: &str, ops: &[Operation]) -> String {
+    let mut path = uuid.to_string();
+    if ops.is_empty() {
+        return path;
+    }
+    for op in ops.iter() {
+        path.push_str("/-/");
+        path.push_str(op.to_string().as_str());
+    }
+    path.push('/');
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_builder() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+        let ops = [Operation::Format("png".to_string())];
+
+        assert_eq!(
+            UrlBuilder::default().url(uuid, &[]),
+            "https://ucarecdn.com/b1026315-8116-4632-8364-607e64fca723/"
+        );
+
+        let config = Config {
+            cdn_base: Some("cdn.example.com/".to_string()),
+        };
+        assert_eq!(
+            UrlBuilder::new(&config).url(uuid, &ops),
+            "https://cdn.example.com/b1026315-8116-4632-8364-607e64fca723/-/format/png/"
+        );
+
+        let config = Config {
+            cdn_base: Some("http://localhost:8080".to_string()),
+        };
+        assert_eq!(UrlBuilder::new(&config).base(), "http://localhost:8080");
+    }
+
+    #[test]
+    fn test_image_format() {
+        assert_eq!(
+            Operation::image_format(ImageFormat::Webp),
+            Operation::Format("webp".to_string())
+        );
+        assert_eq!(
+            Operation::image_format(ImageFormat::Auto).to_string(),
+            "format/auto"
+        );
+    }
+
+    #[test]
+    fn test_with_operations() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+
+        assert_eq!(with_operations(uuid, &[]), uuid);
+        assert_eq!(
+            with_operations(
+                uuid,
+                &[
+                    Operation::Resize {
+                        width: None,
+                        height: Some(800)
+                    },
+                    Operation::Format("png".to_string()),
+                    Operation::Raw("/blur/10/".to_string()),
+                ]
+            ),
+            "b1026315-8116-4632-8364-607e64fca723/-/resize/x800/-/format/png/-/blur/10/",
+        );
+    }
+
+    #[test]
+    fn test_parse_operations() {
+        assert_eq!(parse_operations(""), vec![]);
+        assert_eq!(
+            parse_operations("-/resize/x800/-/crop/100x200/-/grayscale/-/blur/10/"),
+            vec![
+                Operation::Resize {
+                    width: None,
+                    height: Some(800)
+                },
+                Operation::Crop {
+                    width: 100,
+                    height: 200
+                },
+                Operation::Grayscale,
+                Operation::Raw("blur/10".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_operations("crop/100x/"),
+            vec![Operation::Raw("crop/100x".to_string())]
+        );
+    }
+}
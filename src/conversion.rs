@@ -4,26 +4,74 @@
 //! DOC, DOCX, XLS, XLSX, ODT, ODS, RTF, TXT, PDF, JPG, PNG.
 
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
 
 use reqwest::Method;
 use serde::{self, Deserialize, Serialize};
 
-use crate::ucare::{encode_json, rest::Client, Result};
+use crate::ucare::{
+    check_rest_store, encode_json, rest::Client, ClientRef, ErrValue, Error, Result,
+};
+use crate::{cdn, file, group};
+
+pub use crate::ucare::ToStore;
 
 /// Service is used to make calls to conversion API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
+    default_format: Option<DocumentFormat>,
+    cdn: cdn::UrlBuilder,
 }
 
 /// creates an instance of the conversion service
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+        default_format: None,
+        cdn: cdn::UrlBuilder::default(),
+    }
+}
+
+/// creates an instance of the conversion service which shares the client, so it
+/// can be stored along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+        default_format: None,
+        cdn: cdn::UrlBuilder::default(),
+    }
 }
 
 impl Service<'_> {
+    /// Sets target format used by `document_path` for documents converted
+    /// without an explicit one, instead of the API default of pdf
+    pub fn with_default_format(mut self, format: DocumentFormat) -> Self {
+        self.default_format = Some(format);
+        self
+    }
+
+    /// Sets CDN used by `download_result`, e.g. the project custom domain,
+    /// instead of the default `cdn::CDN_URL`
+    pub fn with_cdn(mut self, cdn: cdn::UrlBuilder) -> Self {
+        self.cdn = cdn;
+        self
+    }
+
+    /// Creates path to convert the whole document to the service default
+    /// format, if set. The format can still be changed with `DocumentPath::format`
+    pub fn document_path(&self, uuid: &str) -> DocumentPath {
+        DocumentPath {
+            format: self.default_format,
+            ..DocumentPath::new(uuid)
+        }
+    }
+
     /// Starts document conversion job
     pub fn document(&self, params: JobParams) -> Result<JobResult> {
+        check_rest_store(params.store)?;
         let json = encode_json(&params)?;
         self.client.call::<String, Vec<u8>, JobResult>(
             Method::POST,
@@ -33,6 +81,14 @@ impl Service<'_> {
         )
     }
 
+    /// Downloads the converted document. Multi-page documents converted to
+    /// jpg or png come as a zip archive.
+    pub fn download_result(&self, job_info: &JobInfo) -> Result<impl Read> {
+        file::new_svc(&self.client)
+            .with_cdn(self.cdn.clone())
+            .download(job_info.uuid.as_str())
+    }
+
     /// Gets document conversion job status
     pub fn document_status(&self, token: i32) -> Result<StatusResult> {
         self.client.call::<String, String, StatusResult>(
@@ -45,6 +101,7 @@ impl Service<'_> {
 
     /// Starts video conversion job
     pub fn video(&self, params: JobParams) -> Result<JobResult> {
+        check_rest_store(params.store)?;
         let json = encode_json(&params)?;
         self.client.call::<String, Vec<u8>, JobResult>(
             Method::POST,
@@ -63,6 +120,18 @@ impl Service<'_> {
             None,
         )
     }
+
+    /// Acquires the group of thumbnails generated for the output video by the
+    /// `thumbs` operation. Fails if the job has no thumbnails group.
+    pub fn thumbnails(&self, job_info: &JobInfo) -> Result<group::Info> {
+        match job_info.thumbnails_group_id {
+            Some(ref group_id) => group::new_svc(&self.client).info(group_id),
+            None => Err(Error::with_value(ErrValue::NotFound(format!(
+                "conversion job {} has no thumbnails group",
+                job_info.uuid
+            )))),
+        }
+    }
 }
 
 /// Conversion job params
@@ -94,19 +163,297 @@ pub struct JobParams {
     /// either jpg or png. The method will not work for any other target
     /// formats. :number stands for the one-based number of a page to convert.
     pub paths: Vec<String>,
-    /// Flag indicating if we should store your outputs.
+    /// Flag indicating if we should store your outputs. MUST be either true
+    /// or false, `ToStore::Auto` is rejected.
     pub store: Option<ToStore>,
+    /// Applies to multi-page documents converted to jpg or png. When true, the
+    /// output pages are saved as a file group instead of a zip archive, see
+    /// `JobInfo::group_id`.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_flag"
+    )]
+    pub save_in_group: Option<bool>,
+}
+
+// serializes flags the way the conversion API expects them, as "1" or "0"
+fn serialize_flag<S>(val: &Option<bool>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match *val {
+        Some(true) => serializer.serialize_str("1"),
+        _ => serializer.serialize_str("0"),
+    }
+}
+
+/// Target format of the document conversion. Documents can't be converted to
+/// webp or avif, these are CDN delivery formats, see `cdn::ImageFormat`
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DocumentFormat {
+    /// doc
+    Doc,
+    /// docx
+    Docx,
+    /// xls
+    Xls,
+    /// xlsx
+    Xlsx,
+    /// odt
+    Odt,
+    /// ods
+    Ods,
+    /// rtf
+    Rtf,
+    /// txt
+    Txt,
+    /// pdf, used by the API if format is not specified
+    Pdf,
+    /// jpg
+    Jpg,
+    /// png
+    Png,
+}
+
+impl Display for DocumentFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            DocumentFormat::Doc => "doc",
+            DocumentFormat::Docx => "docx",
+            DocumentFormat::Xls => "xls",
+            DocumentFormat::Xlsx => "xlsx",
+            DocumentFormat::Odt => "odt",
+            DocumentFormat::Ods => "ods",
+            DocumentFormat::Rtf => "rtf",
+            DocumentFormat::Txt => "txt",
+            DocumentFormat::Pdf => "pdf",
+            DocumentFormat::Jpg => "jpg",
+            DocumentFormat::Png => "png",
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
+/// Builds a path for the document conversion job, see `JobParams::paths`
+#[derive(Debug)]
+pub struct DocumentPath {
+    /// UUID of the source document
+    pub uuid: String,
+    /// Target format, the API converts to pdf if it is not set
+    pub format: Option<DocumentFormat>,
+    /// One-based number of a single page to convert. Only jpg and png target
+    /// formats support it.
+    pub page: Option<u32>,
+}
+
+impl DocumentPath {
+    /// Creates path to convert the whole document to the default format
+    pub fn new(uuid: &str) -> Self {
+        DocumentPath {
+            uuid: uuid.to_string(),
+            format: None,
+            page: None,
+        }
+    }
+
+    /// Sets target format
+    pub fn format(mut self, format: DocumentFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Sets a single page to convert
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// Validates params and returns the resulting path:
+    ///   :uuid/document/-/format/:target-format/-/page/:number/
+    pub fn build(&self) -> Result<String> {
+        let mut path = format!("{}/document/", self.uuid);
+        if let Some(ref format) = self.format {
+            path.push_str(format!("-/format/{}/", format).as_str());
+        }
+        if let Some(page) = self.page {
+            match self.format {
+                Some(DocumentFormat::Jpg) | Some(DocumentFormat::Png) => {}
+                _ => {
+                    return Err(Error::with_value(ErrValue::Other(
+                        "page conversion is only supported for jpg and png formats".to_string(),
+                    )))
+                }
+            }
+            if page == 0 {
+                return Err(Error::with_value(ErrValue::Other(
+                    "page number is one-based, got 0".to_string(),
+                )));
+            }
+            path.push_str(format!("-/page/{}/", page).as_str());
+        }
+
+        Ok(path)
+    }
+}
+
+/// Audio-only target format of the video conversion
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AudioFormat {
+    /// mp3
+    Mp3,
+    /// ogg
+    Ogg,
+    /// aac
+    Aac,
+}
+
+impl Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Aac => "aac",
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
+/// Video segment to convert
+#[derive(Debug, PartialEq)]
+pub struct Cut {
+    /// Segment start
+    pub start: Duration,
+    /// Segment length
+    pub length: Duration,
+}
+
+/// Video thumbnails generation params. Generated thumbnails are put into a group,
+/// see `JobInfo::thumbnails_group_id`.
+#[derive(Debug, PartialEq)]
+pub struct ThumbsParams {
+    /// Number of thumbnails to generate, from 1 to 50
+    pub count: u8,
+    /// Zero-based number of a thumbnail to use, less than `count`
+    pub number: Option<u8>,
+}
+
+/// Builds a path for the video conversion job, see `JobParams::paths`
+#[derive(Debug)]
+pub struct VideoPath {
+    /// UUID of the source video
+    pub uuid: String,
+    /// Convert only a segment of the video
+    pub cut: Option<Cut>,
+    /// Thumbnails to generate along with the video
+    pub thumbs: Option<ThumbsParams>,
+    /// Extract only the audio stream in the given format
+    pub audio: Option<AudioFormat>,
+    /// Known duration of the source video, used to validate `cut`
+    pub duration: Option<Duration>,
+}
+
+impl VideoPath {
+    /// Creates path to convert the whole video with default params
+    pub fn new(uuid: &str) -> Self {
+        VideoPath {
+            uuid: uuid.to_string(),
+            cut: None,
+            thumbs: None,
+            audio: None,
+            duration: None,
+        }
+    }
+
+    /// Sets a segment of the video to convert
+    pub fn cut(mut self, start: Duration, length: Duration) -> Self {
+        self.cut = Some(Cut { start, length });
+        self
+    }
+
+    /// Sets thumbnails generation params
+    pub fn thumbs(mut self, thumbs: ThumbsParams) -> Self {
+        self.thumbs = Some(thumbs);
+        self
+    }
+
+    /// Sets audio-only output format. Can't be combined with thumbnails
+    pub fn audio(mut self, format: AudioFormat) -> Self {
+        self.audio = Some(format);
+        self
+    }
+
+    /// Sets known source video duration
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Validates params and returns the resulting path:
+    ///   :uuid/video/-/format/:audio-format/-/cut/:start/:length/-/thumbs~:count/number/:number/
+    pub fn build(&self) -> Result<String> {
+        let mut path = format!("{}/video/", self.uuid);
+        if let Some(ref audio) = self.audio {
+            if self.thumbs.is_some() {
+                return Err(Error::with_value(ErrValue::Other(
+                    "thumbnails can't be generated for audio-only output".to_string(),
+                )));
+            }
+            path.push_str(format!("-/format/{}/", audio).as_str());
+        }
+        if let Some(ref cut) = self.cut {
+            if let Some(duration) = self.duration {
+                if cut.start + cut.length > duration {
+                    return Err(Error::with_value(ErrValue::Other(format!(
+                        "video cut ends at {:?}, which exceeds video duration {:?}",
+                        cut.start + cut.length,
+                        duration
+                    ))));
+                }
+            }
+            path.push_str(
+                format!(
+                    "-/cut/{}/{}/",
+                    format_time(cut.start),
+                    format_time(cut.length)
+                )
+                .as_str(),
+            );
+        }
+        if let Some(ref thumbs) = self.thumbs {
+            if thumbs.count == 0 || thumbs.count > 50 {
+                return Err(Error::with_value(ErrValue::Other(format!(
+                    "thumbnails count must be from 1 to 50, got {}",
+                    thumbs.count
+                ))));
+            }
+            path.push_str(format!("-/thumbs~{}/", thumbs.count).as_str());
+            if let Some(number) = thumbs.number {
+                if number >= thumbs.count {
+                    return Err(Error::with_value(ErrValue::Other(format!(
+                        "thumbnail number must be less than {}, got {}",
+                        thumbs.count, number
+                    ))));
+                }
+                path.push_str(format!("number/{}/", number).as_str());
+            }
+        }
+
+        Ok(path)
+    }
 }
 
-/// MUST be either true or false
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
-pub enum ToStore {
-    /// True
-    #[serde(rename = "true")]
-    True,
-    /// False
-    #[serde(rename = "false")]
-    False,
+// formats duration as HH:MM:SS.mmm
+fn format_time(val: Duration) -> String {
+    let secs = val.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        val.subsec_millis()
+    )
 }
 
 /// Conversion job request result
@@ -118,6 +465,31 @@ pub struct JobResult {
     pub result: Option<Vec<JobInfo>>,
 }
 
+impl JobResult {
+    /// Pairs each requested path with either its job info, matched by
+    /// `original_source`, or its problem description.
+    pub fn by_path(&self) -> HashMap<String, Result<&JobInfo>> {
+        let mut res = HashMap::new();
+        if let Some(ref jobs) = self.result {
+            for job in jobs.iter() {
+                if let Some(ref path) = job.original_source {
+                    res.insert(path.to_string(), Ok(job));
+                }
+            }
+        }
+        if let Some(ref problems) = self.problems {
+            for (path, problem) in problems.iter() {
+                res.insert(
+                    path.to_string(),
+                    Err(Error::with_value(ErrValue::Other(problem.to_string()))),
+                );
+            }
+        }
+
+        res
+    }
+}
+
 /// Conversion job info
 #[derive(Debug, Deserialize)]
 pub struct JobInfo {
@@ -129,9 +501,23 @@ pub struct JobInfo {
     /// Source file identifier including a target format, if present
     pub original_source: Option<String>,
     /// Conversion job token that can be used to get a job status
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub token: Option<i32>,
 }
 
+impl JobInfo {
+    /// Returns the group ID when the output was saved as a file group, which
+    /// is the case for multi-page documents converted with `save_in_group`.
+    /// Group IDs differ from file UUIDs by the `~N` files count suffix.
+    pub fn group_id(&self) -> Option<&str> {
+        if self.uuid.contains('~') {
+            Some(self.uuid.as_str())
+        } else {
+            None
+        }
+    }
+}
+
 /// Conversion job status request result
 #[derive(Debug, Deserialize)]
 pub struct StatusResult {
@@ -147,3 +533,222 @@ pub struct StatusResult {
     /// Result repeats the contents of your processing output
     pub result: JobInfo,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucare::testing;
+
+    #[test]
+    fn test_job_result_by_path() {
+        let data = r#"{
+            "problems": {"bad-uuid/document/-/format/pdf/": "Bad path"},
+            "result": [{
+                "uuid": "d52d7136-a2e5-4338-9f45-affbf83b857d",
+                "original_source": "86c54d9a-3453-4b12-8dcc-49883ae8f084/document/-/format/pdf/",
+                "token": 445630631
+            }]
+        }"#;
+        let res: JobResult = serde_json::from_str(data).unwrap();
+
+        let by_path = res.by_path();
+
+        assert_eq!(by_path.len(), 2);
+        match by_path["86c54d9a-3453-4b12-8dcc-49883ae8f084/document/-/format/pdf/"] {
+            Ok(job) => {
+                assert_eq!(job.uuid, "d52d7136-a2e5-4338-9f45-affbf83b857d");
+                assert_eq!(job.token, Some(445630631));
+            }
+            Err(ref err) => panic!("unexpected error {}", err),
+        }
+        match by_path["bad-uuid/document/-/format/pdf/"] {
+            Ok(ref job) => panic!("unexpected job {:?}", job),
+            Err(ref err) => assert!(err.detail().contains("Bad path")),
+        }
+    }
+
+    #[test]
+    fn test_job_rejects_auto_store() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let conv_svc = new_svc(&client);
+        let params = || JobParams {
+            paths: vec!["d52d7136-a2e5-4338-9f45-affbf83b857d/document/".to_string()],
+            store: Some(ToStore::Auto),
+            save_in_group: None,
+        };
+
+        let err = conv_svc.document(params()).unwrap_err();
+        assert!(err.detail().contains("store MUST be either true or false"));
+        let err = conv_svc.video(params()).unwrap_err();
+        assert!(err.detail().contains("store MUST be either true or false"));
+    }
+
+    #[test]
+    fn test_default_format() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let uuid = "d52d7136-a2e5-4338-9f45-affbf83b857d";
+
+        let conv_svc = new_svc(&client);
+        assert_eq!(
+            conv_svc.document_path(uuid).build().unwrap(),
+            "d52d7136-a2e5-4338-9f45-affbf83b857d/document/"
+        );
+
+        let conv_svc = new_svc(&client).with_default_format(DocumentFormat::Docx);
+        assert_eq!(
+            conv_svc.document_path(uuid).build().unwrap(),
+            "d52d7136-a2e5-4338-9f45-affbf83b857d/document/-/format/docx/"
+        );
+        assert_eq!(
+            conv_svc
+                .document_path(uuid)
+                .format(DocumentFormat::Pdf)
+                .build()
+                .unwrap(),
+            "d52d7136-a2e5-4338-9f45-affbf83b857d/document/-/format/pdf/"
+        );
+    }
+
+    #[test]
+    fn test_thumbnails() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 141\r\nConnection: close\r\n\r\n{\"id\": \"badfc9f7-f88f-4921-9cc0-22e2c08aa2da~2\", \"files_count\": 2, \"cdn_url\": \"https://ucarecdn.com/badfc9f7-f88f-4921-9cc0-22e2c08aa2da~2/\"}",
+        );
+        let client = Client::mock(url.as_str());
+        let conv_svc = new_svc(&client);
+        let mut job_info = JobInfo {
+            uuid: "d52d7136-a2e5-4338-9f45-affbf83b857d".to_string(),
+            thumbnails_group_id: None,
+            original_source: None,
+            token: Some(1),
+        };
+
+        assert!(conv_svc.thumbnails(&job_info).is_err());
+
+        job_info.thumbnails_group_id = Some("badfc9f7-f88f-4921-9cc0-22e2c08aa2da~2".to_string());
+        let info = conv_svc.thumbnails(&job_info).unwrap();
+
+        assert_eq!(info.files_count, 2);
+    }
+
+    #[test]
+    fn test_job_params_save_in_group() {
+        let params = JobParams {
+            paths: vec!["uuid/document/-/format/png/".to_string()],
+            store: None,
+            save_in_group: Some(true),
+        };
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(
+            json,
+            r#"{"paths":["uuid/document/-/format/png/"],"store":null,"save_in_group":"1"}"#
+        );
+
+        let job = JobInfo {
+            uuid: "d6d34fa9-addd-472c-868d-2e5c105f9fcd~3".to_string(),
+            thumbnails_group_id: None,
+            original_source: None,
+            token: None,
+        };
+        assert_eq!(
+            job.group_id(),
+            Some("d6d34fa9-addd-472c-868d-2e5c105f9fcd~3")
+        );
+    }
+
+    #[test]
+    fn test_document_path() {
+        let uuid = "d6d34fa9-addd-472c-868d-2e5c105f9fcd";
+
+        assert_eq!(
+            DocumentPath::new(uuid).build().unwrap(),
+            "d6d34fa9-addd-472c-868d-2e5c105f9fcd/document/",
+        );
+        assert_eq!(
+            DocumentPath::new(uuid)
+                .format(DocumentFormat::Png)
+                .page(2)
+                .build()
+                .unwrap(),
+            "d6d34fa9-addd-472c-868d-2e5c105f9fcd/document/-/format/png/-/page/2/",
+        );
+
+        assert!(DocumentPath::new(uuid).page(1).build().is_err());
+        assert!(DocumentPath::new(uuid)
+            .format(DocumentFormat::Pdf)
+            .page(1)
+            .build()
+            .is_err());
+        assert!(DocumentPath::new(uuid)
+            .format(DocumentFormat::Jpg)
+            .page(0)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_video_path_cut() {
+        let uuid = "d6d34fa9-addd-472c-868d-2e5c105f9fcd";
+
+        assert_eq!(
+            VideoPath::new(uuid)
+                .cut(Duration::from_secs(65), Duration::from_secs(10))
+                .build()
+                .unwrap(),
+            "d6d34fa9-addd-472c-868d-2e5c105f9fcd/video/-/cut/00:01:05.000/00:00:10.000/",
+        );
+
+        assert!(VideoPath::new(uuid)
+            .cut(Duration::from_secs(65), Duration::from_secs(10))
+            .duration(Duration::from_secs(70))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_video_path_thumbs() {
+        let uuid = "d6d34fa9-addd-472c-868d-2e5c105f9fcd";
+
+        assert_eq!(
+            VideoPath::new(uuid)
+                .thumbs(ThumbsParams {
+                    count: 10,
+                    number: Some(3),
+                })
+                .build()
+                .unwrap(),
+            "d6d34fa9-addd-472c-868d-2e5c105f9fcd/video/-/thumbs~10/number/3/",
+        );
+
+        assert!(VideoPath::new(uuid)
+            .thumbs(ThumbsParams {
+                count: 51,
+                number: None,
+            })
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_video_path_audio() {
+        let uuid = "d6d34fa9-addd-472c-868d-2e5c105f9fcd";
+
+        assert_eq!(
+            VideoPath::new(uuid)
+                .audio(AudioFormat::Mp3)
+                .cut(Duration::from_secs(0), Duration::from_secs(30))
+                .build()
+                .unwrap(),
+            "d6d34fa9-addd-472c-868d-2e5c105f9fcd/video/-/format/mp3/-/cut/00:00:00.000/00:00:30.000/",
+        );
+
+        assert!(VideoPath::new(uuid)
+            .audio(AudioFormat::Ogg)
+            .thumbs(ThumbsParams {
+                count: 1,
+                number: None,
+            })
+            .build()
+            .is_err());
+    }
+}
@@ -8,24 +8,53 @@
 
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Display};
+use std::io::Read;
+use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use reqwest::{Method, Url};
 use serde::{self, Deserialize, Serialize};
 use serde_json;
 
-use crate::ucare::{encode_json, rest::Client, IntoUrlQuery, Result};
+use crate::cdn::{self, Operation};
+use crate::ucare::{
+    check_rest_store, clamp_limit, encode_json, rest::Client, ClientRef, ErrValue, Error,
+    IntoUrlQuery, Result,
+};
+
+pub use crate::ucare::ToStore;
 
 /// Service is used to make calls to file API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
+    cdn: cdn::UrlBuilder,
 }
 
 /// creates an instance of the file service
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+        cdn: cdn::UrlBuilder::default(),
+    }
+}
+
+/// creates an instance of the file service which shares the client, so it can be stored
+/// along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+        cdn: cdn::UrlBuilder::default(),
+    }
 }
 
 impl Service<'_> {
+    /// Sets CDN used by `download`, e.g. the project custom domain, instead
+    /// of the default `cdn::CDN_URL`
+    pub fn with_cdn(mut self, cdn: cdn::UrlBuilder) -> Self {
+        self.cdn = cdn;
+        self
+    }
+
     /// Acquires some file specific info
     pub fn info(&self, file_id: &str) -> Result<Info> {
         self.client.call::<String, String, Info>(
@@ -36,6 +65,49 @@ impl Service<'_> {
         )
     }
 
+    /// Checks if the file exists, other errors than not found are returned as is
+    pub fn exists(&self, file_id: &str) -> Result<bool> {
+        match self.info(file_id) {
+            Ok(_) => Ok(true),
+            Err(err) if err.is_not_found() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Acquires info of the file derived from the source file with the passed
+    /// operations, e.g. by a conversion. Fails if the source file has no such
+    /// variation.
+    pub fn info_of_variation(&self, source_uuid: &str, ops: &[Operation]) -> Result<Info> {
+        let source = self.info(source_uuid)?;
+        let uuid = source
+            .typed_variations()
+            .and_then(|variations| variations.find(ops).map(|uuid| uuid.to_string()));
+
+        match uuid {
+            Some(uuid) => self.info(uuid.as_str()),
+            None => Err(Error::with_value(ErrValue::NotFound(format!(
+                "file {} has no variation {}",
+                source_uuid,
+                cdn::with_operations("", ops)
+            )))),
+        }
+    }
+
+    /// Downloads the file contents from the CDN, the returned response
+    /// implements `Read` so the contents can be streamed
+    pub fn download(&self, file_id: &str) -> Result<impl Read> {
+        let url = Url::parse(self.cdn.url(file_id, &[]).as_str())?;
+        self.client.download(url)
+    }
+
+    /// Acquires info for each of the passed files, results come in the same order.
+    ///
+    /// There is no batch info endpoint, so it costs one request per file. Requests
+    /// are made sequentially over the client connection pool.
+    pub fn info_many(&self, file_ids: &[&str]) -> Vec<Result<Info>> {
+        file_ids.iter().map(|id| self.info(id)).collect()
+    }
+
     /// Returns a list of files
     ///
     /// ```rust,ignore
@@ -75,6 +147,56 @@ impl Service<'_> {
         self.client.call_url::<String, List>(Method::GET, url, None)
     }
 
+    /// Gets page following the passed one, returns None if it is the last page
+    pub fn get_next_page(&self, list: &List) -> Result<Option<List>> {
+        match list.next {
+            Some(ref url) => Ok(Some(self.get_page(url)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets page preceding the passed one, returns None if it is the first page
+    pub fn get_prev_page(&self, list: &List) -> Result<Option<List>> {
+        match list.previous {
+            Some(ref url) => Ok(Some(self.get_page(url)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the list pages matching the params, use it to
+    /// process files in batches of up to `limit` files. Pages are requested
+    /// lazily, one at a time; the iteration stops after the first error.
+    ///
+    /// ```rust,ignore
+    /// for page in file_svc.pages(params) {
+    ///     let files = page?.results.unwrap_or_default();
+    ///     db.insert_all(&files)?;
+    /// }
+    /// ```
+    pub fn pages(&self, params: ListParams) -> Pages<'_> {
+        Pages {
+            client: &self.client,
+            params: Some(params),
+            next: None,
+        }
+    }
+
+    /// Walks all the list pages matching the params and calls `f` with each file,
+    /// only the current page is kept in memory. Stops at the first error
+    /// returned by the API or by `f`.
+    pub fn for_each<F>(&self, params: ListParams, mut f: F) -> Result<()>
+    where
+        F: FnMut(Info) -> Result<()>,
+    {
+        for page in self.pages(params) {
+            for info in page?.results.unwrap_or_default() {
+                f(info)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Store a single file by its id
     pub fn store(&self, file_id: &str) -> Result<Info> {
         self.client.call::<String, String, Info>(
@@ -87,7 +209,9 @@ impl Service<'_> {
 
     /// Used to store multiple files in one go. Up to 100 files are
     /// supported per request.
+    /// Malformed file ids are reported as an error before making the request.
     pub fn batch_store(&self, file_ids: &[&str]) -> Result<BatchInfo> {
+        check_uuids(file_ids)?;
         let json = encode_json(&file_ids)?;
         self.client.call::<String, Vec<u8>, BatchInfo>(
             Method::PUT,
@@ -109,7 +233,9 @@ impl Service<'_> {
 
     /// Used to delete multiple files in one go. Up to 100 files are
     /// supported per request.
+    /// Malformed file ids are reported as an error before making the request.
     pub fn batch_delete(&self, file_ids: &[&str]) -> Result<BatchInfo> {
+        check_uuids(file_ids)?;
         let json = encode_json(&file_ids)?;
         self.client.call::<String, Vec<u8>, BatchInfo>(
             Method::DELETE,
@@ -119,8 +245,201 @@ impl Service<'_> {
         )
     }
 
-    /// Copy is the APIv05 version of the LocalCopy and RemoteCopy, use them instead
+    /// Returns file metadata as a key-value map. Requires APIv0.7
+    pub fn metadata(&self, file_id: &str) -> Result<HashMap<String, String>> {
+        self.client.call::<String, String, HashMap<String, String>>(
+            Method::GET,
+            format!("/files/{}/metadata/", file_id),
+            None,
+            None,
+        )
+    }
+
+    /// Returns all metadata keys set for the file
+    pub fn metadata_keys(&self, file_id: &str) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self.metadata(file_id)?.keys().cloned().collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Sets a single file metadata key, returns the stored value. Requires APIv0.7
+    pub fn set_metadata(&self, file_id: &str, key: &str, value: &str) -> Result<String> {
+        let json = encode_json(&value)?;
+        self.client.call::<String, Vec<u8>, String>(
+            Method::PUT,
+            format!("/files/{}/metadata/{}/", file_id, key),
+            None,
+            Some(json),
+        )
+    }
+
+    /// Merges the passed metadata into the file metadata and returns updated
+    /// file info. Requires APIv0.7
+    ///
+    /// The API has no endpoint to update the whole metadata object, so keys are
+    /// set one by one in sorted order and the update is not atomic: if a key
+    /// fails, the keys set before it are kept and listed in the error.
+    pub fn update(&self, file_id: &str, params: UpdateFileParams) -> Result<Info> {
+        let mut keys: Vec<&str> = params.metadata.keys().map(|key| key.as_str()).collect();
+        keys.sort();
+        for (index, key) in keys.iter().enumerate() {
+            if let Err(err) = self.set_metadata(file_id, key, params.metadata[*key].as_str()) {
+                return Err(err.with_context(format!(
+                    "metadata update of file {} failed at key {}, keys already set: [{}]",
+                    file_id,
+                    key,
+                    keys[..index].join(", ")
+                )));
+            }
+        }
+
+        self.info(file_id)
+    }
+
+    /// Deletes a single file metadata key
+    pub fn delete_metadata(&self, file_id: &str, key: &str) -> Result<()> {
+        let res = self.client.call::<String, String, String>(
+            Method::DELETE,
+            format!("/files/{}/metadata/{}/", file_id, key),
+            None,
+            None,
+        );
+        if let Err(err) = res {
+            if !err.to_string().contains("EOF") {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all file metadata keys one by one
+    pub fn clear_metadata(&self, file_id: &str) -> Result<()> {
+        for key in self.metadata_keys(file_id)?.iter() {
+            self.delete_metadata(file_id, key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores all files matching the list params. Goes through all the list pages
+    /// first and then stores found files in batches of `BATCH_MAX_SIZE`.
+    pub fn store_all_matching(&self, params: ListParams) -> Result<BatchSummary> {
+        let file_ids = self.list_uuids(params)?;
+        let mut summary = BatchSummary::default();
+        for chunk in file_ids.chunks(BATCH_MAX_SIZE) {
+            let ids: Vec<&str> = chunk.iter().map(|id| id.as_str()).collect();
+            summary.add(self.batch_store(&ids)?);
+        }
+
+        Ok(summary)
+    }
+
+    /// Deletes all files matching the list params. Goes through all the list pages
+    /// first and then deletes found files in batches of `BATCH_MAX_SIZE`.
+    pub fn delete_all_matching(&self, params: ListParams) -> Result<BatchSummary> {
+        let file_ids = self.list_uuids(params)?;
+        let mut summary = BatchSummary::default();
+        for chunk in file_ids.chunks(BATCH_MAX_SIZE) {
+            let ids: Vec<&str> = chunk.iter().map(|id| id.as_str()).collect();
+            summary.add(self.batch_delete(&ids)?);
+        }
+
+        Ok(summary)
+    }
+
+    /// Returns UUIDs of all files matching the params, walking all the list pages.
+    ///
+    /// The API has no field selection, so full pages are still fetched, but only
+    /// UUIDs are kept.
+    pub fn list_uuids(&self, params: ListParams) -> Result<Vec<String>> {
+        let mut file_ids = Vec::new();
+        self.for_each(params, |info| {
+            file_ids.push(info.uuid);
+            Ok(())
+        })?;
+
+        Ok(file_ids)
+    }
+
+    /// Returns all files matching the params which were uploaded from the passed
+    /// source, e.g. "facebook" or "gdrive".
+    ///
+    /// The API has no source filter, so all the list pages are fetched and
+    /// filtered on the client side.
+    pub fn list_by_source(&self, source: &str, params: ListParams) -> Result<Vec<Info>> {
+        let mut files = Vec::new();
+        self.for_each(params, |info| {
+            if info.source.as_deref() == Some(source) {
+                files.push(info);
+            }
+            Ok(())
+        })?;
+
+        Ok(files)
+    }
+
+    /// Returns files uploaded within the `[after, before)` range, oldest first.
+    ///
+    /// Pages are requested from `after` on and the listing stops as soon as a file
+    /// uploaded at `before` or later is met, so the rest of the files are not fetched.
+    pub fn list_uploaded_between(
+        &self,
+        after: DateTime<Utc>,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Info>> {
+        let mut files = Vec::new();
+        for page in self.pages(ListParams::uploaded_after(after)) {
+            for info in page?.results.unwrap_or_default() {
+                let crossed = match info.datetime_uploaded {
+                    Some(ref val) => match DateTime::parse_from_rfc3339(val) {
+                        Ok(uploaded) => uploaded >= before,
+                        Err(_) => false,
+                    },
+                    None => false,
+                };
+                if crossed {
+                    return Ok(files);
+                }
+                files.push(info);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Counts project files by their state. Makes a list request for each state
+    /// and takes the `total` of the response, so files are not traversed.
+    pub fn counts(&self) -> Result<FileCounts> {
+        let total = |stored: Option<bool>, removed: bool| -> Result<i32> {
+            let list = self.list(ListParams {
+                removed: Some(removed),
+                stored,
+                limit: Some(1),
+                ordering: None,
+                from: None,
+            })?;
+            Ok(list.total.unwrap_or(0))
+        };
+
+        let stored = total(Some(true), false)?;
+        let unstored = total(Some(false), false)?;
+        let removed = total(None, true)?;
+
+        Ok(FileCounts {
+            stored,
+            unstored,
+            removed,
+            total: stored + unstored + removed,
+        })
+    }
+
+    /// Copy is the APIv05 version of the LocalCopy and RemoteCopy, use them instead.
+    /// local_copy covers copying to the default storage and remote_copy
+    /// copying to a custom one when `target` is set
+    #[deprecated(note = "use local_copy or remote_copy")]
     pub fn copy(&self, params: CopyParams) -> Result<LocalCopyInfo> {
+        check_uuids(&[source_uuid(params.source.as_str())])?;
         let json = encode_json(&params)?;
         self.client.call::<String, Vec<u8>, LocalCopyInfo>(
             Method::POST,
@@ -132,15 +451,10 @@ impl Service<'_> {
 
     /// Used to copy original files or their modified versions to
     /// default storage. Source files MAY either be stored or just uploaded and MUST
-    /// NOT be deleted
-    pub fn local_copy(&self, mut params: CopyParams) -> Result<LocalCopyInfo> {
-        if let None = params.store {
-            params.store = Some(ToStore::False);
-        }
-        if let None = params.make_public {
-            params.make_public = Some(MakePublic::True);
-        }
-
+    /// NOT be deleted. See `CopyParams::builder` for the defaults applied.
+    pub fn local_copy(&self, params: CopyParams) -> Result<LocalCopyInfo> {
+        check_uuids(&[source_uuid(params.source.as_str())])?;
+        check_rest_store(params.store)?;
         let json = encode_json(&params)?;
 
         self.client.call::<String, Vec<u8>, LocalCopyInfo>(
@@ -153,23 +467,74 @@ impl Service<'_> {
 
     /// Used to copy original files or their modified versions to a custom
     /// storage. Source files MAY either be stored or just uploaded and MUST NOT be
-    /// deleted.
-    pub fn remote_copy(&self, mut params: CopyParams) -> Result<RemoteCopyInfo> {
-        if let None = params.make_public {
-            params.make_public = Some(MakePublic::True);
-        }
-
+    /// deleted. See `CopyParams::builder` for the defaults applied.
+    ///
+    /// If the destination file already exists, the API responds with conflict
+    /// and `already_exists` is set in the returned info instead of an error.
+    pub fn remote_copy(&self, params: CopyParams) -> Result<RemoteCopyInfo> {
+        check_uuids(&[source_uuid(params.source.as_str())])?;
+        check_rest_store(params.store)?;
         let json = encode_json(&params)?;
 
-        self.client.call::<String, Vec<u8>, RemoteCopyInfo>(
+        let res = self.client.call::<String, Vec<u8>, RemoteCopyInfo>(
             Method::POST,
             format!("/files/remote_copy/"),
             None,
             Some(json),
-        )
+        );
+        match res {
+            Err(ref err) if err.is_conflict() => Ok(RemoteCopyInfo {
+                already_exists: true,
+                result: None,
+            }),
+            res => res,
+        }
     }
 }
 
+/// Checks if the passed string is a well-formed file UUID, e.g.
+/// "b1026315-8116-4632-8364-607e64fca723"
+pub fn is_valid_uuid(val: &str) -> bool {
+    let parts: Vec<&str> = val.split('-').collect();
+    let lens = [8, 4, 4, 4, 12];
+    parts.len() == lens.len()
+        && parts
+            .iter()
+            .zip(lens.iter())
+            .all(|(part, len)| part.len() == *len && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// returns an error listing all malformed ids, if any
+fn check_uuids(file_ids: &[&str]) -> Result<()> {
+    let malformed: Vec<&str> = file_ids
+        .iter()
+        .filter(|id| !is_valid_uuid(id))
+        .cloned()
+        .collect();
+    if malformed.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::with_value(ErrValue::Other(format!(
+        "malformed file ids: {}",
+        malformed.join(", ")
+    ))))
+}
+
+// extracts file UUID from the copy source which is either a CDN URL or UUID
+// with optional operations
+fn source_uuid(source: &str) -> &str {
+    let path = match source.find("://") {
+        Some(pos) => match source[pos + 3..].find('/') {
+            Some(slash) => &source[pos + 3 + slash + 1..],
+            None => "",
+        },
+        None => source,
+    };
+
+    path.split('/').next().unwrap_or("")
+}
+
 /// Info holds file specific information
 #[derive(Debug, Deserialize)]
 pub struct Info {
@@ -194,6 +559,7 @@ pub struct Info {
     /// Original file name taken from uploaded file.
     pub original_filename: Option<String>,
     /// File size in bytes.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub size: Option<i32>,
     /// API resource URL for a particular file.
     pub url: Option<String>,
@@ -207,6 +573,240 @@ pub struct Info {
     pub source: Option<String>,
     /// Dictionary of file categories with it\"s confidence.
     pub rekognition_info: Option<HashMap<String, f32>>,
+    /// Structured file content information. Returned only by APIv0.7, legacy
+    /// `image_info` and `video_info` fields are kept for older versions.
+    pub content_info: Option<ContentInfo>,
+    /// File metadata. Returned only by APIv0.7
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = match self.size {
+            Some(val) => format!("{} bytes", val),
+            None => "unknown size".to_string(),
+        };
+        let mime = match self.mime_type {
+            Some(ref val) => val.as_str(),
+            None => "unknown type",
+        };
+
+        write!(f, "{} ({}, {})", self.uuid, size, mime)
+    }
+}
+
+impl Info {
+    /// Returns typed `variations`, see `Variations` for details
+    pub fn typed_variations(&self) -> Option<Variations> {
+        self.variations.as_ref().map(Variations::from_value)
+    }
+
+    /// Returns typed `source`, see `UploadSource`
+    pub fn upload_source(&self) -> Option<UploadSource> {
+        self.source.as_deref().map(UploadSource::from)
+    }
+
+    /// Returns up to `n` `rekognition_info` categories with the highest
+    /// confidence, sorted by confidence descending
+    pub fn top_categories(&self, n: usize) -> Vec<(String, f32)> {
+        let mut categories = self.sorted_categories();
+        categories.truncate(n);
+        categories
+    }
+
+    /// Returns `rekognition_info` categories with confidence above the
+    /// threshold, sorted by confidence descending
+    pub fn categories_above(&self, threshold: f32) -> Vec<(String, f32)> {
+        self.sorted_categories()
+            .into_iter()
+            .filter(|(_, confidence)| *confidence > threshold)
+            .collect()
+    }
+
+    fn sorted_categories(&self) -> Vec<(String, f32)> {
+        let mut categories: Vec<(String, f32)> = match self.rekognition_info {
+            Some(ref info) => info
+                .iter()
+                .map(|(name, confidence)| (name.to_string(), *confidence))
+                .collect(),
+            None => Vec::new(),
+        };
+        categories.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+
+        categories
+    }
+}
+
+/// Origin of the uploaded file taken from the `source` field
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
+pub enum UploadSource {
+    /// "local", uploaded from a device
+    Local,
+    /// "url", uploaded from a URL
+    Url,
+    /// "facebook"
+    Facebook,
+    /// "instagram"
+    Instagram,
+    /// "gdrive", Google Drive
+    GDrive,
+    /// "gphotos", Google Photos
+    GPhotos,
+    /// "dropbox"
+    Dropbox,
+    /// "onedrive"
+    OneDrive,
+    /// "box"
+    Box,
+    /// Any source unknown to this version of the library
+    Other(String),
+}
+
+impl From<&str> for UploadSource {
+    fn from(val: &str) -> Self {
+        match val {
+            "local" => UploadSource::Local,
+            "url" => UploadSource::Url,
+            "facebook" => UploadSource::Facebook,
+            "instagram" => UploadSource::Instagram,
+            "gdrive" => UploadSource::GDrive,
+            "gphotos" => UploadSource::GPhotos,
+            "dropbox" => UploadSource::Dropbox,
+            "onedrive" => UploadSource::OneDrive,
+            "box" => UploadSource::Box,
+            _ => UploadSource::Other(val.to_string()),
+        }
+    }
+}
+
+impl From<String> for UploadSource {
+    fn from(val: String) -> Self {
+        UploadSource::from(val.as_str())
+    }
+}
+
+impl Display for UploadSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            UploadSource::Local => "local",
+            UploadSource::Url => "url",
+            UploadSource::Facebook => "facebook",
+            UploadSource::Instagram => "instagram",
+            UploadSource::GDrive => "gdrive",
+            UploadSource::GPhotos => "gphotos",
+            UploadSource::Dropbox => "dropbox",
+            UploadSource::OneDrive => "onedrive",
+            UploadSource::Box => "box",
+            UploadSource::Other(ref val) => val.as_str(),
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
+/// Variations maps the path with CDN operations used to create a derived file
+/// (e.g. "video/-/format/mp4/") to that file UUID.
+#[derive(Debug, Default, PartialEq)]
+pub struct Variations(pub HashMap<String, String>);
+
+impl Variations {
+    /// Builds variations from the raw `variations` value. Entries of unexpected
+    /// shape are skipped, use the raw value if you need them.
+    pub fn from_value(val: &serde_json::Value) -> Self {
+        let mut map = HashMap::new();
+        if let Some(obj) = val.as_object() {
+            for (path, uuid) in obj.iter() {
+                if let Some(uuid) = uuid.as_str() {
+                    map.insert(path.to_string(), uuid.to_string());
+                }
+            }
+        }
+
+        Variations(map)
+    }
+
+    /// Returns UUID of the file derived with the passed operations, variation
+    /// paths are matched regardless of the "video/" or "document/" prefix and
+    /// surrounding slashes
+    pub fn find(&self, ops: &[Operation]) -> Option<&str> {
+        let wanted = ops
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<String>>()
+            .join("/-/");
+
+        self.0
+            .iter()
+            .find(|(path, _)| {
+                let mut path = path.trim_matches('/');
+                for prefix in ["video/", "document/", "-/"].iter() {
+                    path = path.trim_start_matches(prefix);
+                }
+                path.trim_matches('/') == wanted
+            })
+            .map(|(_, uuid)| uuid.as_str())
+    }
+}
+
+/// ContentInfo holds file content information returned by APIv0.7
+#[derive(Debug, Deserialize)]
+pub struct ContentInfo {
+    /// MIME type breakdown.
+    pub mime: Option<ContentInfoMime>,
+    /// Image metadata, if file is an image.
+    pub image: Option<ImageInfo>,
+    /// Video metadata, if file is a video.
+    pub video: Option<ContentInfoVideo>,
+}
+
+/// MIME type information
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ContentInfoMime {
+    /// Full MIME type, e.g. "image/jpeg".
+    pub mime: String,
+    /// Type part of the MIME type, e.g. "image".
+    #[serde(rename = "type")]
+    pub mime_type: String,
+    /// Subtype part of the MIME type, e.g. "jpeg".
+    pub subtype: String,
+}
+
+/// Video related information in the APIv0.7 shape
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ContentInfoVideo {
+    /// Video duration in milliseconds.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
+    pub duration: Option<f32>,
+    /// Video format (MP4 for example).
+    pub format: Option<String>,
+    /// Video bitrate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
+    pub bitrate: Option<f32>,
+    /// Audio streams information
+    pub audio: Option<Vec<ContentInfoAudio>>,
+    /// Video streams information
+    pub video: Option<Vec<VideoInfoVideo>>,
+}
+
+/// Audio stream information in the APIv0.7 shape
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ContentInfoAudio {
+    /// Audio stream bitrate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
+    pub bitrate: Option<f32>,
+    /// Audio stream codec.
+    pub codec: Option<String>,
+    /// Audio stream sample rate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
+    pub sample_rate: Option<f32>,
+    /// Audio stream number of channels.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
+    pub channels: Option<i32>,
 }
 
 /// ImageInfo holds image-specific information
@@ -215,14 +815,17 @@ pub struct ImageInfo {
     /// Image color mode.
     pub color_mode: Option<ColorMode>,
     /// Image orientation from EXIF.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub orientation: Option<i32>,
     /// Image format.
     pub format: Option<String>,
     /// Image sequence
     pub sequence: Option<bool>,
     /// Image height in pixels.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub height: Option<i32>,
     /// Image width in pixels.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub width: Option<i32>,
     /// Image geo location.
     pub geo_location: Option<ImageInfoGeoLocation>,
@@ -232,12 +835,44 @@ pub struct ImageInfo {
     pub dpi: Option<Vec<f32>>,
 }
 
+impl ImageInfo {
+    /// Whether the image must be rotated or mirrored to be displayed upright,
+    /// i.e. EXIF orientation is known and is not the normal one (1)
+    pub fn needs_rotation(&self) -> bool {
+        match self.orientation {
+            Some(val) => (2..=8).contains(&val),
+            None => false,
+        }
+    }
+
+    /// Clockwise rotation in degrees to display the image upright, derived from
+    /// EXIF orientation. Mirroring, if any, is applied before the rotation.
+    /// None if orientation is unknown.
+    pub fn exif_rotation_degrees(&self) -> Option<u16> {
+        match self.orientation? {
+            1 | 2 => Some(0),
+            3 | 4 => Some(180),
+            5 | 8 => Some(270),
+            6 | 7 => Some(90),
+            _ => None,
+        }
+    }
+
+    /// Latitude and longitude of the image, if both are known
+    pub fn lat_lng(&self) -> Option<(f32, f32)> {
+        let geo = self.geo_location.as_ref()?;
+        Some((geo.latitude?, geo.longitude?))
+    }
+}
+
 /// Image geo location
 #[derive(Debug, Deserialize)]
 pub struct ImageInfoGeoLocation {
     /// Location latitude.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub latitude: Option<f32>,
     /// Location longitude.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub longitude: Option<f32>,
 }
 
@@ -270,16 +905,21 @@ pub enum ColorMode {
     HSV,
     /// LAB
     LAB,
+    /// Any color mode unknown to this version of the library
+    #[serde(other)]
+    Other,
 }
 
 /// Video related information
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct VideoInfo {
     /// Video duration in milliseconds.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub duration: Option<f32>,
     /// Video format (MP4 for example).
     pub format: Option<String>,
     /// Video bitrate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub bitrate: Option<f32>,
     /// Audio information
     pub audio: Option<VideoInfoAudio>,
@@ -291,10 +931,12 @@ pub struct VideoInfo {
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct VideoInfoAudio {
     /// Audio stream metadata.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub bitrate: Option<f32>,
     /// Audio stream codec.
     pub codec: Option<String>,
     /// Audio stream sample rate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub sample_rate: Option<f32>,
     /// Audio stream number of channels.
     pub channels: Option<String>,
@@ -304,12 +946,16 @@ pub struct VideoInfoAudio {
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct VideoInfoVideo {
     /// Video stream image height.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub height: Option<f32>,
     /// Video stream image width.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub width: Option<f32>,
     /// Video stream frame rate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub frame_rate: Option<f32>,
     /// Video stream bitrate.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub bitrate: Option<f32>,
     /// Video stream codec.
     pub codec: Option<String>,
@@ -325,7 +971,8 @@ pub struct ListParams {
     /// The default is unset: both stored and not stored files are returned
     pub stored: Option<bool>,
     /// Specifies preferred amount of files in a list for a single
-    /// response. Defaults to 100, while the maximum is 1000
+    /// response. Defaults to 100, while the maximum is 1000, larger values
+    /// are lowered to it
     pub limit: Option<i32>,
     /// Specifies the way files are sorted in a returned list.
     /// By default is set to datetime_uploaded.
@@ -337,6 +984,7 @@ pub struct ListParams {
 
 /// Specifies the way files are sorted in a returned list.
 /// By default is set to datetime_uploaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Ordering {
     /// "datetime_uploaded"
     DatetimeUploaded,
@@ -361,6 +1009,30 @@ impl Display for Ordering {
     }
 }
 
+// format of the `from` cursor for the datetime orderings
+const LIST_FROM_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+impl ListParams {
+    /// Params to list files uploaded after the passed date-time, oldest first
+    pub fn uploaded_after(datetime: DateTime<Utc>) -> Self {
+        ListParams {
+            removed: None,
+            stored: None,
+            limit: None,
+            ordering: Some(Ordering::DatetimeUploaded),
+            from: Some(datetime.format(LIST_FROM_FORMAT).to_string()),
+        }
+    }
+
+    /// Params to list files uploaded before the passed date-time, newest first
+    pub fn uploaded_before(datetime: DateTime<Utc>) -> Self {
+        ListParams {
+            ordering: Some(Ordering::DatetimeUploadedNeg),
+            ..ListParams::uploaded_after(datetime)
+        }
+    }
+}
+
 impl IntoUrlQuery for ListParams {
     fn into_query(self) -> String {
         let mut q = String::new();
@@ -380,7 +1052,7 @@ impl IntoUrlQuery for ListParams {
 
         q.push_str("limit=");
         if let Some(val) = self.limit {
-            q.push_str(val.to_string().as_str());
+            q.push_str(clamp_limit(val).to_string().as_str());
         } else {
             q.push_str("1000");
         }
@@ -403,6 +1075,37 @@ impl IntoUrlQuery for ListParams {
     }
 }
 
+/// Iterator over file list pages returned by `pages`
+pub struct Pages<'a> {
+    client: &'a Client,
+    params: Option<ListParams>,
+    next: Option<String>,
+}
+
+impl Iterator for Pages<'_> {
+    type Item = Result<List>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let svc = new_svc(self.client);
+        let res = match self.params.take() {
+            Some(params) => svc.list(params),
+            None => svc.get_page(self.next.take()?.as_str()),
+        };
+        if let Ok(ref list) = res {
+            self.next = list.next.clone();
+        }
+
+        Some(res)
+    }
+}
+
+/// Params for the file update
+#[derive(Debug, Default)]
+pub struct UpdateFileParams {
+    /// Metadata keys to set, other file metadata keys are left unchanged
+    pub metadata: HashMap<String, String>,
+}
+
 /// Holds a list of files
 #[derive(Debug, Deserialize)]
 pub struct List {
@@ -414,25 +1117,29 @@ pub struct List {
     pub previous: Option<String>,
     /// A total number of objects of the queried type. For files, the queried type depends on
     /// the stored and removed query parameters.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub total: Option<i32>,
     /// Number of objects per page.
+    #[serde(default, deserialize_with = "crate::ucare::de::opt_number")]
     pub per_page: Option<i32>,
 }
 
-/// MUST be either true or false
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
-pub enum ToStore {
-    /// True
-    #[serde(rename = "true")]
-    True,
-    /// False
-    #[serde(rename = "false")]
-    False,
+/// Number of project files by their state, returned by `counts`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileCounts {
+    /// Stored files, not removed
+    pub stored: i32,
+    /// Temporary files, not removed
+    pub unstored: i32,
+    /// Removed files
+    pub removed: i32,
+    /// All of the above
+    pub total: i32,
 }
 
 /// MUST be either true or false. true to make copied files available via public links,
 /// false to reverse the behavior.
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
 pub enum MakePublic {
     /// True
     #[serde(rename = "true")]
@@ -445,26 +1152,85 @@ pub enum MakePublic {
 /// The parameter is used to specify file names Uploadcare passes to a custom storage.
 /// In case the parameter is omitted, we use pattern of your custom storage.
 /// Use any combination of allowed values.
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Pattern {
     /// Default
-    #[serde(rename = "${default}")]
     Default,
     /// AutoFilename
-    #[serde(rename = "${filename} ${effects} ${ext}")]
     AutoFilename,
     /// Effects
-    #[serde(rename = "${effects}")]
     Effects,
     /// Filename
-    #[serde(rename = "${filename}")]
     Filename,
     /// Uuid
-    #[serde(rename = "${uuid}")]
     Uuid,
     /// Ext
-    #[serde(rename = "${ext}")]
     Ext,
+    /// Concatenation of the passed parts, e.g. `${uuid}${ext}`
+    Custom(Vec<PatternPart>),
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Pattern::Default => write!(f, "{}", PatternPart::Default),
+            Pattern::AutoFilename => write!(
+                f,
+                "{} {} {}",
+                PatternPart::Filename,
+                PatternPart::Effects,
+                PatternPart::Ext
+            ),
+            Pattern::Effects => write!(f, "{}", PatternPart::Effects),
+            Pattern::Filename => write!(f, "{}", PatternPart::Filename),
+            Pattern::Uuid => write!(f, "{}", PatternPart::Uuid),
+            Pattern::Ext => write!(f, "{}", PatternPart::Ext),
+            Pattern::Custom(ref parts) => {
+                for part in parts {
+                    write!(f, "{}", part)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Single placeholder of the custom Pattern
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum PatternPart {
+    /// `${default}`
+    Default,
+    /// `${uuid}`
+    Uuid,
+    /// `${filename}`
+    Filename,
+    /// `${effects}`
+    Effects,
+    /// `${ext}`
+    Ext,
+}
+
+impl Display for PatternPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            PatternPart::Default => "${default}",
+            PatternPart::Uuid => "${uuid}",
+            PatternPart::Filename => "${filename}",
+            PatternPart::Effects => "${effects}",
+            PatternPart::Ext => "${ext}",
+        };
+
+        write!(f, "{}", val)
+    }
 }
 
 /// CopyParams is used when copy original files or their modified
@@ -475,7 +1241,7 @@ pub struct CopyParams {
     /// Source is a CDN URL or just ID (UUID) of a file subjected to copy
     pub source: String,
     /// Store parameter only applies to the Uploadcare storage and MUST
-    /// be either true or false.
+    /// be either true or false, `ToStore::Auto` is rejected.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store: Option<ToStore>,
     /// MakePublic is applicable to custom storage only. MUST be either true or
@@ -496,6 +1262,100 @@ pub struct CopyParams {
     pub pattern: Option<Pattern>,
 }
 
+impl CopyParams {
+    /// Creates copy params with the defaults of `CopyParams::builder` from either
+    /// a bare UUID, with optional operations, or a ucarecdn.com URL, e.g.
+    /// "https://ucarecdn.com/b1026315-8116-4632-8364-607e64fca723/-/resize/x800/".
+    /// The source is normalized to the "uuid/-/operations/" form.
+    pub fn from_cdn_url(url: &str) -> Result<CopyParams> {
+        let path = if url.contains("://") {
+            let parsed = Url::parse(url)?;
+            match parsed.host_str() {
+                Some(host) if host == "ucarecdn.com" || host.ends_with(".ucarecdn.com") => {}
+                _ => {
+                    return Err(Error::with_value(ErrValue::Other(format!(
+                        "not a ucarecdn.com URL: {}",
+                        url
+                    ))))
+                }
+            }
+            parsed.path().trim_start_matches('/').to_string()
+        } else {
+            url.to_string()
+        };
+
+        check_uuids(&[source_uuid(path.as_str())])?;
+
+        Ok(CopyParams::builder(path.as_str()).build())
+    }
+
+    /// Creates a builder for the copy params of the passed source which is
+    /// a CDN URL or just ID (UUID) of a file
+    pub fn builder(source: &str) -> CopyParamsBuilder {
+        CopyParamsBuilder {
+            source: source.to_string(),
+            store: None,
+            make_public: None,
+            target: None,
+            pattern: None,
+        }
+    }
+}
+
+/// Builds CopyParams applying the defaults for the omitted fields
+#[derive(Debug)]
+pub struct CopyParamsBuilder {
+    source: String,
+    store: Option<ToStore>,
+    make_public: Option<MakePublic>,
+    target: Option<String>,
+    pattern: Option<Pattern>,
+}
+
+impl CopyParamsBuilder {
+    /// Sets the Uploadcare storage store behaviour
+    pub fn store(mut self, val: ToStore) -> Self {
+        self.store = Some(val);
+        self
+    }
+
+    /// Sets whether copied files are available via public links
+    pub fn make_public(mut self, val: MakePublic) -> Self {
+        self.make_public = Some(val);
+        self
+    }
+
+    /// Sets the custom storage name to copy the file to
+    pub fn target(mut self, val: String) -> Self {
+        self.target = Some(val);
+        self
+    }
+
+    /// Sets the file names pattern used in a custom storage
+    pub fn pattern(mut self, val: Pattern) -> Self {
+        self.pattern = Some(val);
+        self
+    }
+
+    /// Returns params ready to use. MakePublic defaults to true, store
+    /// defaults to false unless the target custom storage is set
+    pub fn build(self) -> CopyParams {
+        let store = match (self.store, &self.target) {
+            (Some(val), _) => Some(val),
+            (None, None) => Some(ToStore::False),
+            (None, Some(_)) => None,
+        };
+
+        CopyParams {
+            source: self.source,
+            store,
+            make_public: Some(self.make_public.unwrap_or(MakePublic::True)),
+            target: self.target,
+            pattern: self.pattern,
+        }
+    }
+}
+
 /// Holds local_copy response data
 #[derive(Debug, Deserialize)]
 pub struct LocalCopyInfo {
@@ -515,6 +1375,32 @@ pub struct RemoteCopyInfo {
     pub result: Option<String>,
 }
 
+/// Maximum number of files supported per batch request
+pub const BATCH_MAX_SIZE: usize = 100;
+
+/// Holds results of the batch operation over multiple batch requests
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    /// Number of successfully operated files
+    pub succeeded: usize,
+    /// Number of files that failed
+    pub failed: usize,
+    /// Map of failed files IDs and associated problems
+    pub problems: HashMap<String, String>,
+}
+
+impl BatchSummary {
+    fn add(&mut self, info: BatchInfo) {
+        if let Some(result) = info.result {
+            self.succeeded += result.len();
+        }
+        if let Some(problems) = info.problems {
+            self.failed += problems.len();
+            self.problems.extend(problems);
+        }
+    }
+}
+
 /// Holds batch operation response data
 #[derive(Debug, Deserialize)]
 pub struct BatchInfo {
@@ -523,3 +1409,431 @@ pub struct BatchInfo {
     /// Results describes successfully operated files
     pub result: Option<Vec<Info>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucare::testing;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_download_custom_cdn() {
+        let (url, requests) = testing::capture_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndata",
+        );
+        let client = Client::mock(url.as_str());
+        let cdn = cdn::UrlBuilder::new(&cdn::Config {
+            cdn_base: Some(url.to_string()),
+        });
+
+        let mut contents = String::new();
+        new_svc(&client)
+            .with_cdn(cdn)
+            .download("b1026315-8116-4632-8364-607e64fca723")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "data");
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("GET /b1026315-8116-4632-8364-607e64fca723/ "));
+    }
+
+    #[test]
+    fn test_exists() {
+        let url = testing::serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 24\r\nConnection: close\r\n\r\n{\"detail\": \"Not found.\"}",
+        );
+        let client = Client::mock(url.as_str());
+
+        assert!(!new_svc(&client)
+            .exists("b1026315-8116-4632-8364-607e64fca723")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_shared_svc() {
+        let url = testing::serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 24\r\nConnection: close\r\n\r\n{\"detail\": \"Not found.\"}",
+        );
+        let client = Arc::new(Client::mock(url.as_str()));
+
+        let file_svc: Service<'static> = new_shared_svc(client.clone());
+        drop(client);
+
+        assert!(!file_svc
+            .exists("b1026315-8116-4632-8364-607e64fca723")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_update() {
+        let (url, requests) = testing::capture_requests(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n\"1\"",
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n\"2\"",
+            "HTTP/1.1 200 OK\r\nContent-Length: 15\r\nConnection: close\r\n\r\n{\"uuid\": \"abc\"}",
+        ]);
+        let client = Client::mock(url.as_str());
+        let mut metadata = HashMap::new();
+        metadata.insert("b".to_string(), "2".to_string());
+        metadata.insert("a".to_string(), "1".to_string());
+
+        let info = new_svc(&client)
+            .update("abc", UpdateFileParams { metadata })
+            .unwrap();
+
+        assert_eq!(info.uuid, "abc");
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("PUT /files/abc/metadata/a/ "));
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("PUT /files/abc/metadata/b/ "));
+        assert!(requests.recv().unwrap().starts_with("GET /files/abc/ "));
+    }
+
+    #[test]
+    fn test_update_partial_failure() {
+        let (url, requests) = testing::capture_requests(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\n\"1\"",
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 25\r\nConnection: close\r\n\r\n{\"detail\": \"Bad request\"}",
+        ]);
+        let client = Client::mock(url.as_str());
+        let mut metadata = HashMap::new();
+        metadata.insert("a".to_string(), "1".to_string());
+        metadata.insert("b".to_string(), "2".to_string());
+        metadata.insert("c".to_string(), "3".to_string());
+
+        let err = new_svc(&client)
+            .update("abc", UpdateFileParams { metadata })
+            .unwrap_err();
+
+        assert!(err.detail().contains(
+            "metadata update of file abc failed at key b, keys already set: [a]: Bad request"
+        ));
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("PUT /files/abc/metadata/a/ "));
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("PUT /files/abc/metadata/b/ "));
+        match err.value() {
+            ErrValue::BadRequest(_) => {}
+            _ => panic!("expected bad request error"),
+        }
+    }
+
+    #[test]
+    fn test_pages() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 41\r\nConnection: close\r\n\r\n{\"results\": [], \"next\": null, \"total\": 0}",
+        );
+        let client = Client::mock(url.as_str());
+        let params = ListParams {
+            removed: None,
+            stored: None,
+            limit: None,
+            ordering: None,
+            from: None,
+        };
+
+        let file_svc = new_svc(&client);
+        let mut pages = file_svc.pages(params);
+
+        assert_eq!(pages.next().unwrap().unwrap().total, Some(0));
+        assert!(pages.next().is_none());
+    }
+
+    #[test]
+    fn test_list_uuids() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 57\r\nConnection: close\r\n\r\n{\"results\": [{\"uuid\": \"a\"}, {\"uuid\": \"b\"}], \"next\": null}",
+        );
+        let client = Client::mock(url.as_str());
+        let params = ListParams {
+            removed: None,
+            stored: None,
+            limit: None,
+            ordering: None,
+            from: None,
+        };
+
+        let uuids = new_svc(&client).list_uuids(params).unwrap();
+
+        assert_eq!(uuids, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_list_by_source() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 99\r\nConnection: close\r\n\r\n{\"results\": [{\"uuid\": \"a\", \"source\": \"facebook\"}, {\"uuid\": \"b\", \"source\": \"gdrive\"}], \"next\": null}",
+        );
+        let client = Client::mock(url.as_str());
+        let params = ListParams {
+            removed: None,
+            stored: None,
+            limit: None,
+            ordering: None,
+            from: None,
+        };
+
+        let files = new_svc(&client).list_by_source("gdrive", params).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].uuid, "b");
+    }
+
+    #[test]
+    fn test_for_each() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 57\r\nConnection: close\r\n\r\n{\"results\": [{\"uuid\": \"a\"}, {\"uuid\": \"b\"}], \"next\": null}",
+        );
+        let client = Client::mock(url.as_str());
+        let params = ListParams {
+            removed: None,
+            stored: None,
+            limit: None,
+            ordering: None,
+            from: None,
+        };
+
+        let mut uuids = Vec::new();
+        let err = new_svc(&client)
+            .for_each(params, |info| {
+                if info.uuid == "b" {
+                    return Err(Error::with_value(ErrValue::Other("stop".to_string())));
+                }
+                uuids.push(info.uuid);
+                Ok(())
+            })
+            .unwrap_err();
+
+        assert_eq!(uuids, vec!["a".to_string()]);
+        assert!(err.detail().contains("stop"));
+    }
+
+    #[test]
+    fn test_list_params_uploaded() {
+        let datetime = Utc.ymd(2020, 5, 17).and_hms(10, 30, 0);
+
+        assert_eq!(
+            ListParams::uploaded_after(datetime).into_query(),
+            "removed=false&limit=1000&ordering=datetime_uploaded&from=2020-05-17T10:30:00"
+        );
+        assert_eq!(
+            ListParams::uploaded_before(datetime).into_query(),
+            "removed=false&limit=1000&ordering=-datetime_uploaded&from=2020-05-17T10:30:00"
+        );
+    }
+
+    #[test]
+    fn test_upload_source() {
+        assert_eq!(UploadSource::from("gdrive"), UploadSource::GDrive);
+        assert_eq!(
+            UploadSource::from("evernote"),
+            UploadSource::Other("evernote".to_string())
+        );
+        assert_eq!(
+            UploadSource::Other("evernote".to_string()).to_string(),
+            "evernote"
+        );
+
+        let source: UploadSource = serde_json::from_str(r#""facebook""#).unwrap();
+        assert_eq!(source, UploadSource::Facebook);
+        assert_eq!(source.to_string(), "facebook");
+    }
+
+    #[test]
+    fn test_is_valid_uuid() {
+        assert!(is_valid_uuid("b1026315-8116-4632-8364-607e64fca723"));
+        assert!(!is_valid_uuid("b1026315-8116-4632-8364-607e64fca72"));
+        assert!(!is_valid_uuid("b1026315-8116-4632-8364-607e64fca72z"));
+        assert!(!is_valid_uuid("b10263158116-4632-8364-607e64fca723"));
+        assert!(!is_valid_uuid(""));
+    }
+
+    #[test]
+    fn test_source_uuid() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+
+        assert_eq!(source_uuid(uuid), uuid);
+        assert_eq!(
+            source_uuid(format!("{}/-/resize/x800/", uuid).as_str()),
+            uuid
+        );
+        assert_eq!(
+            source_uuid(format!("https://ucarecdn.com/{}/-/resize/x800/", uuid).as_str()),
+            uuid
+        );
+    }
+
+    #[test]
+    fn test_copy_params_builder() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+
+        assert_eq!(
+            CopyParams::builder(uuid).build(),
+            CopyParams {
+                source: uuid.to_string(),
+                store: Some(ToStore::False),
+                make_public: Some(MakePublic::True),
+                target: None,
+                pattern: None,
+            }
+        );
+        assert_eq!(
+            CopyParams::builder(uuid)
+                .target("s3".to_string())
+                .make_public(MakePublic::False)
+                .pattern(Pattern::Uuid)
+                .build(),
+            CopyParams {
+                source: uuid.to_string(),
+                store: None,
+                make_public: Some(MakePublic::False),
+                target: Some("s3".to_string()),
+                pattern: Some(Pattern::Uuid),
+            }
+        );
+        assert_eq!(
+            CopyParams::builder(uuid).store(ToStore::True).build().store,
+            Some(ToStore::True)
+        );
+    }
+
+    #[test]
+    fn test_copy_rejects_auto_store() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let file_svc = new_svc(&client);
+        let params = || {
+            CopyParams::builder("b1026315-8116-4632-8364-607e64fca723")
+                .store(ToStore::Auto)
+                .build()
+        };
+
+        let err = file_svc.local_copy(params()).unwrap_err();
+        assert!(err.detail().contains("store MUST be either true or false"));
+        let err = file_svc.remote_copy(params()).unwrap_err();
+        assert!(err.detail().contains("store MUST be either true or false"));
+    }
+
+    #[test]
+    fn test_pattern_serialize() {
+        let auto = serde_json::to_string(&Pattern::AutoFilename).unwrap();
+        assert_eq!(auto, r#""${filename} ${effects} ${ext}""#);
+
+        let custom = Pattern::Custom(vec![PatternPart::Uuid, PatternPart::Ext]);
+        let custom = serde_json::to_string(&custom).unwrap();
+        assert_eq!(custom, r#""${uuid}${ext}""#);
+    }
+
+    #[test]
+    fn test_image_info_orientation() {
+        let expected = [
+            (1, 0),
+            (2, 0),
+            (3, 180),
+            (4, 180),
+            (5, 270),
+            (6, 90),
+            (7, 90),
+            (8, 270),
+        ];
+        for &(orientation, degrees) in expected.iter() {
+            let info: ImageInfo =
+                serde_json::from_str(&format!(r#"{{"orientation": {}}}"#, orientation)).unwrap();
+            assert_eq!(info.needs_rotation(), orientation != 1);
+            assert_eq!(
+                info.exif_rotation_degrees(),
+                Some(degrees),
+                "orientation {}",
+                orientation
+            );
+        }
+
+        let info: ImageInfo = serde_json::from_str("{}").unwrap();
+        assert!(!info.needs_rotation());
+        assert_eq!(info.exif_rotation_degrees(), None);
+    }
+
+    #[test]
+    fn test_image_info_lat_lng() {
+        let info: ImageInfo = serde_json::from_str(
+            r#"{"geo_location": {"latitude": 55.62013611111111, "longitude": 37.66299166666666}}"#,
+        )
+        .unwrap();
+        assert_eq!(info.lat_lng(), Some((55.62013611111111, 37.66299166666666)));
+
+        let info: ImageInfo =
+            serde_json::from_str(r#"{"geo_location": {"latitude": 55.6}}"#).unwrap();
+        assert_eq!(info.lat_lng(), None);
+    }
+
+    #[test]
+    fn test_copy_params_from_cdn_url() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+
+        assert_eq!(CopyParams::from_cdn_url(uuid).unwrap().source, uuid);
+        assert_eq!(
+            CopyParams::from_cdn_url(
+                format!("https://ucarecdn.com/{}/-/resize/x800/", uuid).as_str()
+            )
+            .unwrap()
+            .source,
+            format!("{}/-/resize/x800/", uuid)
+        );
+
+        assert!(
+            CopyParams::from_cdn_url(format!("https://example.com/{}/", uuid).as_str()).is_err()
+        );
+        assert!(CopyParams::from_cdn_url("https://ucarecdn.com/not-a-uuid/").is_err());
+    }
+
+    #[test]
+    fn test_variations_find() {
+        let variations = Variations::from_value(&serde_json::json!({
+            "video/-/format/mp4/": "c3c54e5c-1c00-4b2c-9d4a-3b0c1b5a9f3e",
+            "document/-/format/png/-/page/1/": "4dd9fa4d-35ae-4e2a-8f47-8e0c7c3f7b0e",
+        }));
+
+        assert_eq!(
+            variations.find(&[Operation::Format("mp4".to_string())]),
+            Some("c3c54e5c-1c00-4b2c-9d4a-3b0c1b5a9f3e")
+        );
+        assert_eq!(
+            variations.find(&[
+                Operation::Format("png".to_string()),
+                Operation::Raw("page/1".to_string())
+            ]),
+            Some("4dd9fa4d-35ae-4e2a-8f47-8e0c7c3f7b0e")
+        );
+        assert_eq!(variations.find(&[Operation::Grayscale]), None);
+    }
+
+    #[test]
+    fn test_rekognition_categories() {
+        let info: Info = serde_json::from_str(
+            r#"{
+                "uuid": "b1026315-8116-4632-8364-607e64fca723",
+                "rekognition_info": {"Car": 0.9, "Vehicle": 0.95, "Tree": 0.1}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            info.top_categories(2),
+            vec![("Vehicle".to_string(), 0.95), ("Car".to_string(), 0.9)]
+        );
+        assert_eq!(
+            info.categories_above(0.5),
+            vec![("Vehicle".to_string(), 0.95), ("Car".to_string(), 0.9)]
+        );
+        assert_eq!(info.top_categories(10).len(), 3);
+    }
+}
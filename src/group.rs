@@ -15,25 +15,39 @@
 //!   badfc9f7-f88f-4921-9cc0-22e2c08aa2da~12
 
 use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
 
 use reqwest::{Method, Url};
 use serde::Deserialize;
 
-use crate::ucare::{rest::Client, IntoUrlQuery, Result};
+use crate::file::is_valid_uuid;
+use crate::ucare::{clamp_limit, rest::Client, ClientRef, ErrValue, Error, IntoUrlQuery, Result};
 
 /// Service is used to make calls to group API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
 }
 
 /// creates an instance of the group service
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+    }
+}
+
+/// creates an instance of the group service which shares the client, so it can be stored
+/// along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+    }
 }
 
 impl Service<'_> {
-    /// Acquires some file specific info
-    pub fn info(&self, group_id: &str) -> Result<Info> {
+    /// Acquires some group specific info. Takes either a `GroupId` or a string
+    pub fn info<T: Display>(&self, group_id: T) -> Result<Info> {
         self.client.call::<String, String, Info>(
             Method::GET,
             format!("/groups/{}/", group_id),
@@ -81,8 +95,41 @@ impl Service<'_> {
         self.client.call_url::<String, List>(Method::GET, url, None)
     }
 
+    /// Gets page following the passed one, returns None if it is the last page
+    pub fn get_next_page(&self, list: &List) -> Result<Option<List>> {
+        match list.next {
+            Some(ref url) => Ok(Some(self.get_page(url)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets page preceding the passed one, returns None if it is the first page
+    pub fn get_prev_page(&self, list: &List) -> Result<Option<List>> {
+        match list.previous {
+            Some(ref url) => Ok(Some(self.get_page(url)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes a group by its id. Files within the group are not deleted.
+    pub fn delete<T: Display>(&self, group_id: T) -> Result<()> {
+        let res = self.client.call::<String, String, String>(
+            Method::DELETE,
+            format!("/groups/{}/", group_id),
+            None,
+            None,
+        );
+        if let Err(err) = res {
+            if !err.to_string().contains("EOF") {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Marks all files in group as stored
-    pub fn store(&self, group_id: &str) -> Result<Info> {
+    pub fn store<T: Display>(&self, group_id: T) -> Result<Info> {
         self.client.call::<String, String, Info>(
             Method::PUT,
             format!("/groups/{}/storage/", group_id),
@@ -92,8 +139,46 @@ impl Service<'_> {
     }
 }
 
-/// Info holds group specific information
-#[derive(Debug, Deserialize)]
+/// Group identifier: a UUID followed by a "~" and the number of files in the
+/// group, e.g. "badfc9f7-f88f-4921-9cc0-22e2c08aa2da~12"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupId {
+    /// Group UUID
+    pub uuid: String,
+    /// Number of files in the group
+    pub count: u32,
+}
+
+impl FromStr for GroupId {
+    type Err = Error;
+
+    fn from_str(val: &str) -> Result<Self> {
+        let malformed =
+            || Error::with_value(ErrValue::Other(format!("malformed group id: {}", val)));
+
+        let pos = val.find('~').ok_or_else(malformed)?;
+        let (uuid, count) = (&val[..pos], &val[pos + 1..]);
+        if !is_valid_uuid(uuid) {
+            return Err(malformed());
+        }
+        let count = count.parse::<u32>().map_err(|_| malformed())?;
+
+        Ok(GroupId {
+            uuid: uuid.to_string(),
+            count,
+        })
+    }
+}
+
+impl Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}~{}", self.uuid, self.count)
+    }
+}
+
+/// Info holds group specific information. Groups are compared and hashed by
+/// their ID only
+#[derive(Debug, Clone, Deserialize)]
 pub struct Info {
     /// group identifier
     pub id: String,
@@ -101,16 +186,39 @@ pub struct Info {
     pub datetime_created: Option<String>,
     /// date and time when a group was stored
     pub datetime_stored: Option<String>,
+    /// date and time when a group was deleted, if any
+    pub datetime_deleted: Option<String>,
     /// number of files in a group
     pub files_count: i32,
     /// public CDN URL for a group
     pub cdn_url: String,
 }
 
+impl Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({} files)", self.id, self.files_count)
+    }
+}
+
+impl PartialEq for Info {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Info {}
+
+impl Hash for Info {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 /// Holds all possible params for for the list method
 pub struct ListParams {
     /// Specifies preferred amount of groups in a list for a single
-    /// response. Defaults to 100, while the maximum is 1000
+    /// response. Defaults to 100, while the maximum is 1000, larger values
+    /// are lowered to it
     pub limit: Option<i32>,
     /// Specifies the way groups are sorted in a returned list.
     /// By default is set to datetime_created.
@@ -122,6 +230,7 @@ pub struct ListParams {
 
 /// Specifies the way groups are sorted in a returned list.
 /// By default is set to datetime_created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Ordering {
     /// datetime_created
     CreatedAtAsc,
@@ -146,7 +255,7 @@ impl IntoUrlQuery for ListParams {
 
         q.push_str("limit=");
         if let Some(val) = self.limit {
-            q.push_str(val.to_string().as_str());
+            q.push_str(clamp_limit(val).to_string().as_str());
         } else {
             q.push_str("100");
         }
@@ -183,3 +292,30 @@ pub struct List {
     /// Number of objects per page.
     pub per_page: Option<f32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_id() {
+        let id = "badfc9f7-f88f-4921-9cc0-22e2c08aa2da~12"
+            .parse::<GroupId>()
+            .unwrap();
+
+        assert_eq!(id.uuid, "badfc9f7-f88f-4921-9cc0-22e2c08aa2da");
+        assert_eq!(id.count, 12);
+        assert_eq!(id.to_string(), "badfc9f7-f88f-4921-9cc0-22e2c08aa2da~12");
+
+        assert!("badfc9f7-f88f-4921-9cc0-22e2c08aa2da"
+            .parse::<GroupId>()
+            .is_err());
+        assert!("badfc9f7-f88f-4921-9cc0-22e2c08aa2da~"
+            .parse::<GroupId>()
+            .is_err());
+        assert!("badfc9f7-f88f-4921-9cc0-22e2c08aa2da~-1"
+            .parse::<GroupId>()
+            .is_err());
+        assert!("badfc9f7~12".parse::<GroupId>().is_err());
+    }
+}
@@ -24,6 +24,7 @@
 //! let config = ucare::RestConfig {
 //!     sign_based_auth: true,
 //!     api_version: ucare::RestApiVersion::V05,
+//!     ..Default::default()
 //! };
 //!
 //! let rest_client = ucare::RestClient::new(config, creds).unwrap();
@@ -57,16 +58,24 @@
 //!
 //! In binary choose a logging implementation and initialize it in the runtime of the program.
 
+#[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+compile_error!(
+    "either `rustls-tls` or `native-tls` feature must be enabled, the API is HTTPS only"
+);
+
 mod ucare;
 
 #[cfg(feature = "rest")]
 pub use crate::ucare::rest::{
-    ApiVersion as RestApiVersion, Client as RestClient, Config as RestConfig,
+    ApiVersion as RestApiVersion, AuthMode as RestAuthMode, Client as RestClient,
+    Config as RestConfig,
 };
 
 #[cfg(feature = "upload")]
 pub use crate::ucare::upload::{Client as UploadClient, Config as UploadConfig};
 
+pub mod cdn;
+
 #[cfg(feature = "rest")]
 pub mod conversion;
 #[cfg(feature = "rest")]
@@ -81,4 +90,7 @@ pub mod webhook;
 #[cfg(feature = "upload")]
 pub mod upload;
 
-pub use crate::ucare::{ApiCreds, ErrValue, Error, Result};
+#[cfg(all(feature = "rest", feature = "upload"))]
+pub use crate::upload::upload_and_store;
+
+pub use crate::ucare::{ApiCreds, ErrValue, Error, Mime, Result, ToStore};
@@ -1,20 +1,31 @@
 //! Holds all primitives and logic around the project resource.
 
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use reqwest::Method;
 use serde::Deserialize;
 
-use crate::ucare::{rest::Client, Result};
+use crate::ucare::{rest::Client, ClientRef, Result};
 
-/// Service is used to make calls to webhook API.
+/// Service is used to make calls to project API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
 }
 
-/// creates an instance of the webhook service
+/// creates an instance of the project service
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+    }
+}
+
+/// creates an instance of the project service which shares the client, so it can be stored
+/// along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+    }
 }
 
 impl Service<'_> {
@@ -0,0 +1,80 @@
+//! Deserialization helpers for the API responses
+
+use std::fmt::Display;
+use std::result;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer};
+
+// the API is not consistent across endpoints and versions and sometimes
+// sends numbers as JSON strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString<T> {
+    Number(T),
+    String(String),
+}
+
+/// Deserializes a number which may also come as a numeric string.
+/// Use with `#[serde(deserialize_with = "crate::ucare::de::number")]`
+pub(crate) fn number<'de, D, T>(deserializer: D) -> result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match NumberOrString::<T>::deserialize(deserializer)? {
+        NumberOrString::Number(val) => Ok(val),
+        NumberOrString::String(val) => val.trim().parse().map_err(de::Error::custom),
+    }
+}
+
+/// Same as `number`, but for optional fields, an empty string is treated as None.
+/// Use with `#[serde(default, deserialize_with = "crate::ucare::de::opt_number")]`
+pub(crate) fn opt_number<'de, D, T>(deserializer: D) -> result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match Option::<NumberOrString<T>>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(val)) => Ok(Some(val)),
+        Some(NumberOrString::String(ref val)) if val.trim().is_empty() => Ok(None),
+        Some(NumberOrString::String(val)) => {
+            val.trim().parse().map(Some).map_err(de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Data {
+        #[serde(deserialize_with = "number")]
+        size: u32,
+        #[serde(default, deserialize_with = "opt_number")]
+        duration: Option<f32>,
+    }
+
+    #[test]
+    fn test_number_or_string() {
+        let data: Data = serde_json::from_str(r#"{"size": 10, "duration": 1.5}"#).unwrap();
+        assert_eq!(data.size, 10);
+        assert_eq!(data.duration, Some(1.5));
+
+        let data: Data = serde_json::from_str(r#"{"size": "10", "duration": "1.5"}"#).unwrap();
+        assert_eq!(data.size, 10);
+        assert_eq!(data.duration, Some(1.5));
+
+        let data: Data = serde_json::from_str(r#"{"size": 10, "duration": ""}"#).unwrap();
+        assert_eq!(data.duration, None);
+
+        let data: Data = serde_json::from_str(r#"{"size": 10}"#).unwrap();
+        assert_eq!(data.duration, None);
+
+        assert!(serde_json::from_str::<Data>(r#"{"size": "ten"}"#).is_err());
+    }
+}
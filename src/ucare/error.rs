@@ -2,6 +2,7 @@
 //!
 //! TODO: improve
 
+use std::error;
 use std::fmt;
 use std::io;
 
@@ -19,6 +20,8 @@ pub struct Error {
     detail: String,
     #[serde(skip_deserializing)]
     value: ErrValue,
+    #[serde(skip_deserializing)]
+    request_id: Option<String>,
 }
 
 impl Error {
@@ -32,24 +35,79 @@ impl Error {
         Error {
             detail: val.to_string(),
             value: val,
+            request_id: None,
         }
     }
 
+    /// ID of the request that caused the error, as returned by the API in the
+    /// `X-Request-ID` header. Quote it when contacting Uploadcare support.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub(crate) fn with_request_id(mut self, request_id: Option<String>) -> Error {
+        self.request_id = request_id;
+        self
+    }
+
+    // prefixes the message with what was being done, the value is kept as is
+    // so callers can still match on it
+    pub(crate) fn with_context(mut self, context: String) -> Error {
+        self.detail = format!("{}: {}", context, self.detail);
+        self
+    }
+
     /// Get the `ErrValue` enum for more specific error handling
     pub fn value(self) -> ErrValue {
         self.value
     }
+
+    pub(crate) fn is_not_found(&self) -> bool {
+        matches!(self.value, ErrValue::NotFound(_))
+    }
+
+    pub(crate) fn is_conflict(&self) -> bool {
+        matches!(self.value, ErrValue::Conflict(_))
+    }
+
+    // network errors and server side failures which may pass on retry
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(
+            self.value,
+            ErrValue::Reqwest(_) | ErrValue::ServerError { .. } | ErrValue::TooManyRequests(_)
+        )
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Uploadcare: {}", self.value)
+        write!(f, "Uploadcare: {}", self.detail)?;
+        if let Some(ref id) = self.request_id {
+            write!(f, " (request id: {})", id)?;
+        }
+        Ok(())
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error: {}", self.value)
+        write!(f, "Error: {}", self.detail)?;
+        if let Some(ref id) = self.request_id {
+            write!(f, " (request id: {})", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.value {
+            ErrValue::Reqwest(ref err) => Some(err),
+            ErrValue::InputOutput(ref err) => Some(err),
+            ErrValue::SerdeJson(ref err) => Some(err),
+            ErrValue::ParseUrl(ref err) => Some(err),
+            _ => None,
+        }
     }
 }
 
@@ -58,6 +116,7 @@ impl From<io::Error> for Error {
         Error {
             detail: err.to_string(),
             value: ErrValue::InputOutput(err),
+            request_id: None,
         }
     }
 }
@@ -67,6 +126,7 @@ impl From<reqwest::Error> for Error {
         Error {
             detail: err.to_string(),
             value: ErrValue::Reqwest(err),
+            request_id: None,
         }
     }
 }
@@ -76,6 +136,7 @@ impl From<serde_json::Error> for Error {
         Error {
             detail: err.to_string(),
             value: ErrValue::SerdeJson(err),
+            request_id: None,
         }
     }
 }
@@ -85,11 +146,16 @@ impl From<url::ParseError> for Error {
         Error {
             detail: err.to_string(),
             value: ErrValue::ParseUrl(err),
+            request_id: None,
         }
     }
 }
 
-/// Represents possible errors returned by the library
+/// Represents possible errors returned by the library.
+///
+/// New variants may be added in minor releases, so matches should include
+/// a wildcard arm.
+#[non_exhaustive]
 pub enum ErrValue {
     /// Endpoint parameters error
     BadRequest(String),
@@ -103,8 +169,18 @@ pub enum ErrValue {
     NotAcceptable(String),
     /// Payload too large
     PayloadTooLarge(String),
+    /// Request conflicts with the resource state, e.g. a copy destination
+    /// already exists
+    Conflict(String),
     /// Request was throttled
     TooManyRequests(i32),
+    /// Server side errors (5xx status codes)
+    ServerError {
+        /// Response status code
+        status: u16,
+        /// Response body
+        body: String,
+    },
 
     /// Errors returned from reqwest underlying lib
     Reqwest(reqwest::Error),
@@ -121,27 +197,28 @@ pub enum ErrValue {
 
 impl fmt::Display for ErrValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let prefix = "Uploadcare";
-
         match *self {
-            ErrValue::BadRequest(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::Unauthorized(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::Forbidden(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::NotFound(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::NotAcceptable(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::PayloadTooLarge(ref msg) => write!(f, "{}: {}", prefix, msg),
-            ErrValue::TooManyRequests(ref retry_after) => write!(
-                f,
-                "{}: too many requests, retry after {}",
-                prefix, retry_after
-            ),
-
-            ErrValue::Reqwest(ref err) => write!(f, "{}: {}", prefix, err),
-            ErrValue::InputOutput(ref err) => write!(f, "{}: {}", prefix, err),
-            ErrValue::SerdeJson(ref err) => write!(f, "{}: {}", prefix, err),
-            ErrValue::ParseUrl(ref err) => write!(f, "{}: {}", prefix, err),
-
-            ErrValue::Other(ref msg) => write!(f, "{}: {}", prefix, msg),
+            ErrValue::BadRequest(ref msg) => write!(f, "{}", msg),
+            ErrValue::Unauthorized(ref msg) => write!(f, "{}", msg),
+            ErrValue::Forbidden(ref msg) => write!(f, "{}", msg),
+            ErrValue::NotFound(ref msg) => write!(f, "{}", msg),
+            ErrValue::NotAcceptable(ref msg) => write!(f, "{}", msg),
+            ErrValue::PayloadTooLarge(ref msg) => write!(f, "{}", msg),
+            ErrValue::Conflict(ref msg) => write!(f, "{}", msg),
+            ErrValue::TooManyRequests(ref retry_after) => {
+                write!(f, "too many requests, retry after {}", retry_after)
+            }
+            ErrValue::ServerError {
+                ref status,
+                ref body,
+            } => write!(f, "server error {}: {}", status, body),
+
+            ErrValue::Reqwest(ref err) => write!(f, "{}", err),
+            ErrValue::InputOutput(ref err) => write!(f, "{}", err),
+            ErrValue::SerdeJson(ref err) => write!(f, "{}", err),
+            ErrValue::ParseUrl(ref err) => write!(f, "{}", err),
+
+            ErrValue::Other(ref msg) => write!(f, "{}", msg),
         }
     }
 }
@@ -151,3 +228,30 @@ impl Default for ErrValue {
         ErrValue::Other("ErrValue".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Error>();
+
+        let err: Box<dyn error::Error + Send + Sync> =
+            Error::with_value(ErrValue::Other("oops".to_string())).into();
+        assert!(err.to_string().contains("oops"));
+    }
+
+    #[test]
+    fn test_error_with_context() {
+        let err = Error::with_value(ErrValue::TooManyRequests(10))
+            .with_context("upload of part 1 failed".to_string());
+
+        assert!(err.to_string().contains("upload of part 1 failed: "));
+        match err.value() {
+            ErrValue::TooManyRequests(retry_after) => assert_eq!(retry_after, 10),
+            _ => panic!("value is not kept"),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! MIME types helper
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// MIME type of a file. Holds constants for the common types Uploadcare handles,
+/// any other type can be created with `Mime::new`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mime(Cow<'static, str>);
+
+impl Mime {
+    /// image/jpeg
+    pub const IMAGE_JPEG: Mime = Mime(Cow::Borrowed("image/jpeg"));
+    /// image/png
+    pub const IMAGE_PNG: Mime = Mime(Cow::Borrowed("image/png"));
+    /// image/gif
+    pub const IMAGE_GIF: Mime = Mime(Cow::Borrowed("image/gif"));
+    /// image/webp
+    pub const IMAGE_WEBP: Mime = Mime(Cow::Borrowed("image/webp"));
+    /// image/tiff
+    pub const IMAGE_TIFF: Mime = Mime(Cow::Borrowed("image/tiff"));
+    /// image/bmp
+    pub const IMAGE_BMP: Mime = Mime(Cow::Borrowed("image/bmp"));
+    /// image/svg+xml
+    pub const IMAGE_SVG: Mime = Mime(Cow::Borrowed("image/svg+xml"));
+    /// image/heic
+    pub const IMAGE_HEIC: Mime = Mime(Cow::Borrowed("image/heic"));
+    /// video/mp4
+    pub const VIDEO_MP4: Mime = Mime(Cow::Borrowed("video/mp4"));
+    /// video/webm
+    pub const VIDEO_WEBM: Mime = Mime(Cow::Borrowed("video/webm"));
+    /// video/ogg
+    pub const VIDEO_OGG: Mime = Mime(Cow::Borrowed("video/ogg"));
+    /// video/quicktime
+    pub const VIDEO_QUICKTIME: Mime = Mime(Cow::Borrowed("video/quicktime"));
+    /// audio/mpeg
+    pub const AUDIO_MPEG: Mime = Mime(Cow::Borrowed("audio/mpeg"));
+    /// application/pdf
+    pub const PDF: Mime = Mime(Cow::Borrowed("application/pdf"));
+    /// application/msword
+    pub const DOC: Mime = Mime(Cow::Borrowed("application/msword"));
+    /// application/vnd.openxmlformats-officedocument.wordprocessingml.document
+    pub const DOCX: Mime = Mime(Cow::Borrowed(
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ));
+    /// application/vnd.ms-excel
+    pub const XLS: Mime = Mime(Cow::Borrowed("application/vnd.ms-excel"));
+    /// application/vnd.openxmlformats-officedocument.spreadsheetml.sheet
+    pub const XLSX: Mime = Mime(Cow::Borrowed(
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ));
+    /// application/vnd.oasis.opendocument.text
+    pub const ODT: Mime = Mime(Cow::Borrowed("application/vnd.oasis.opendocument.text"));
+    /// application/vnd.oasis.opendocument.spreadsheet
+    pub const ODS: Mime = Mime(Cow::Borrowed(
+        "application/vnd.oasis.opendocument.spreadsheet",
+    ));
+    /// application/rtf
+    pub const RTF: Mime = Mime(Cow::Borrowed("application/rtf"));
+    /// text/plain
+    pub const TEXT_PLAIN: Mime = Mime(Cow::Borrowed("text/plain"));
+    /// application/octet-stream
+    pub const OCTET_STREAM: Mime = Mime(Cow::Borrowed("application/octet-stream"));
+
+    /// Creates MIME type from an arbitrary string
+    pub fn new(val: &str) -> Mime {
+        Mime(Cow::Owned(val.to_string()))
+    }
+
+    /// Guesses MIME type by a file extension, with or without the leading dot.
+    /// Returns None for unknown extensions.
+    pub fn from_extension(ext: &str) -> Option<Mime> {
+        let mime = match ext.trim_start_matches('.').to_lowercase().as_str() {
+            "jpg" | "jpeg" => Mime::IMAGE_JPEG,
+            "png" => Mime::IMAGE_PNG,
+            "gif" => Mime::IMAGE_GIF,
+            "webp" => Mime::IMAGE_WEBP,
+            "tif" | "tiff" => Mime::IMAGE_TIFF,
+            "bmp" => Mime::IMAGE_BMP,
+            "svg" => Mime::IMAGE_SVG,
+            "heic" => Mime::IMAGE_HEIC,
+            "mp4" => Mime::VIDEO_MP4,
+            "webm" => Mime::VIDEO_WEBM,
+            "ogv" => Mime::VIDEO_OGG,
+            "mov" => Mime::VIDEO_QUICKTIME,
+            "mp3" => Mime::AUDIO_MPEG,
+            "pdf" => Mime::PDF,
+            "doc" => Mime::DOC,
+            "docx" => Mime::DOCX,
+            "xls" => Mime::XLS,
+            "xlsx" => Mime::XLSX,
+            "odt" => Mime::ODT,
+            "ods" => Mime::ODS,
+            "rtf" => Mime::RTF,
+            "txt" => Mime::TEXT_PLAIN,
+            _ => return None,
+        };
+
+        Some(mime)
+    }
+
+    /// Returns string representation, e.g. "image/jpeg"
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Mime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for Mime {
+    fn from(val: &str) -> Self {
+        Mime::new(val)
+    }
+}
+
+impl From<Mime> for String {
+    fn from(val: Mime) -> Self {
+        val.0.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(Mime::from_extension("jpg"), Some(Mime::IMAGE_JPEG));
+        assert_eq!(Mime::from_extension(".JPEG"), Some(Mime::IMAGE_JPEG));
+        assert_eq!(Mime::from_extension("unknown"), None);
+        assert_eq!(Mime::new("image/jpeg"), Mime::IMAGE_JPEG);
+        assert_eq!(String::from(Mime::PDF), "application/pdf");
+    }
+}
@@ -1,24 +1,43 @@
 //! Provides the binding for the Uploadcare API.
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
+use std::ops::Deref;
+use std::sync::Arc;
 
-use reqwest::Url;
+#[cfg(feature = "rest")]
+use log::warn;
+use reqwest::{blocking::Response, Proxy, Url};
 use serde::Serialize;
 
 mod error;
 pub use error::{ErrValue, Error, Result};
 
+mod mime;
+pub use mime::Mime;
+
+pub(crate) mod de;
+mod trace;
+
 #[cfg(feature = "rest")]
 pub mod rest;
 
 #[cfg(feature = "upload")]
 pub mod upload;
 
+#[cfg(test)]
+pub(crate) mod testing;
+
 pub(crate) const CLIENT_VERSION: &str = "0.1";
 
+pub(crate) const REQUEST_ID_HEADER: &str = "X-Request-ID";
+
+// maximum number of items per list page accepted by the REST API
+#[cfg(feature = "rest")]
+pub(crate) const MAX_LIST_LIMIT: i32 = 1000;
+
 /// Holds per project API credentials.
 /// You can find your credentials on the uploadcare dashboard.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ApiCreds {
     /// API secret key
     pub secret_key: String,
@@ -26,6 +45,34 @@ pub struct ApiCreds {
     pub pub_key: String,
 }
 
+/// Sets the file storing behaviour. Shared by the REST and Upload API params:
+/// JSON bodies get "true" or "false", while upload form fields get its
+/// `Display` form of "1", "0" or "auto".
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+pub enum ToStore {
+    /// True
+    #[serde(rename = "true")]
+    True,
+    /// False
+    #[serde(rename = "false")]
+    False,
+    /// Auto, use the project settings. Upload API only, REST calls reject it
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+impl Display for ToStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            ToStore::True => "1",
+            ToStore::False => "0",
+            ToStore::Auto => "auto",
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
 pub(crate) trait IntoUrlQuery {
     fn into_query(self) -> String;
 }
@@ -39,6 +86,41 @@ where
     }
 }
 
+pub(crate) fn request_id(res: &Response) -> Option<String> {
+    res.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|val| val.to_str().ok())
+        .map(|val| val.to_string())
+}
+
+// REST API store params are either true or false, auto is not sent as the
+// API would reject it anyway
+#[cfg(feature = "rest")]
+pub(crate) fn check_rest_store(store: Option<ToStore>) -> Result<(), Error> {
+    match store {
+        Some(ToStore::Auto) => Err(Error::with_value(ErrValue::BadRequest(
+            "store MUST be either true or false, auto is only supported by the upload API"
+                .to_string(),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// lowers the list limit to the maximum the API accepts instead of letting
+// the request fail
+#[cfg(feature = "rest")]
+pub(crate) fn clamp_limit(limit: i32) -> i32 {
+    if limit > MAX_LIST_LIMIT {
+        warn!(
+            "list limit {} exceeds the maximum of {}, using the maximum",
+            limit, MAX_LIST_LIMIT
+        );
+        return MAX_LIST_LIMIT;
+    }
+
+    limit
+}
+
 pub(crate) fn encode_json<T>(params: &T) -> Result<Vec<u8>, Error>
 where
     T: ?Sized + Serialize,
@@ -62,3 +144,81 @@ where
     let url = Url::parse(u.as_str())?;
     Ok(url)
 }
+
+// client held by the services, borrowed by `new_svc` or shared by `new_shared_svc`
+pub(crate) enum ClientRef<'a, C> {
+    Borrowed(&'a C),
+    Shared(Arc<C>),
+}
+
+impl<C> Deref for ClientRef<'_, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        match *self {
+            ClientRef::Borrowed(client) => client,
+            ClientRef::Shared(ref client) => client,
+        }
+    }
+}
+
+// validates proxy settings shared by the clients configs
+pub(crate) fn proxy(url: &str, auth: Option<&(String, String)>) -> Result<Proxy, String> {
+    let invalid = |detail: String| format!("Uploadcare: invalid proxy {}: {}", url, detail);
+
+    let parsed = Url::parse(url).map_err(|err| invalid(err.to_string()))?;
+    match parsed.scheme() {
+        "http" | "https" | "socks5" | "socks5h" => {}
+        scheme => return Err(invalid(format!("unsupported scheme {}", scheme))),
+    }
+
+    let mut proxy = Proxy::all(parsed).map_err(|err| invalid(err.to_string()))?;
+    if let Some((username, password)) = auth {
+        proxy = proxy.basic_auth(username, password);
+    }
+
+    Ok(proxy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy() {
+        assert!(proxy("http://proxy.local:3128", None).is_ok());
+        let auth = ("user".to_string(), "pass".to_string());
+        assert!(proxy("https://proxy.local:3128", Some(&auth)).is_ok());
+
+        assert_eq!(
+            proxy("ftp://proxy.local", None).unwrap_err(),
+            "Uploadcare: invalid proxy ftp://proxy.local: unsupported scheme ftp"
+        );
+        assert!(proxy("proxy.local:3128", None).is_err());
+    }
+
+    #[test]
+    fn test_to_store_json() {
+        assert_eq!(serde_json::to_string(&ToStore::True).unwrap(), r#""true""#);
+        assert_eq!(
+            serde_json::to_string(&ToStore::False).unwrap(),
+            r#""false""#
+        );
+        assert_eq!(serde_json::to_string(&ToStore::Auto).unwrap(), r#""auto""#);
+    }
+
+    #[cfg(feature = "rest")]
+    #[test]
+    fn test_clamp_limit() {
+        assert_eq!(clamp_limit(10), 10);
+        assert_eq!(clamp_limit(1000), 1000);
+        assert_eq!(clamp_limit(5000), 1000);
+    }
+
+    #[test]
+    fn test_to_store_form() {
+        assert_eq!(ToStore::True.to_string(), "1");
+        assert_eq!(ToStore::False.to_string(), "0");
+        assert_eq!(ToStore::Auto.to_string(), "auto");
+    }
+}
@@ -1,16 +1,25 @@
 //! Authorization related stuff is here
 
-use crypto::{digest::Digest, hmac::Hmac, mac::Mac, md5::Md5, sha1::Sha1};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
 use itertools::Itertools;
 use log::debug;
+use md5::{Digest, Md5};
 use reqwest::{blocking::Request, header};
+use sha1::Sha1;
 
 use crate::ucare::ApiCreds;
 
 const AUTH_HEADER_KEY: &str = "Authorization";
 const SIMPLE_AUTH_SCHEME: &str = "Uploadcare.Simple";
 const SIGN_BASED_AUTH_SCHEME: &str = "Uploadcare";
-pub const DATE_HEADER_FORMAT: &str = "%a, %d %h %G %T %Z";
+// RFC 2822 date in GMT, e.g. "Mon, 05 Nov 2018 13:14:41 GMT"
+const DATE_HEADER_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Formats the Date header value the signature is calculated over
+pub fn date_header(now: DateTime<Utc>) -> String {
+    now.format(DATE_HEADER_FORMAT).to_string()
+}
 
 pub fn simple(creds: ApiCreds) -> impl Fn(&mut Request) {
     move |req: &mut Request| {
@@ -26,6 +35,8 @@ pub fn simple(creds: ApiCreds) -> impl Fn(&mut Request) {
     }
 }
 
+// The REST API accepts HMAC-SHA1 signatures only, there is no scheme with a
+// stronger algorithm to choose from yet
 pub fn sign_based(creds: ApiCreds) -> impl Fn(&mut Request) {
     move |req| {
         // getting body hash
@@ -35,9 +46,7 @@ pub fn sign_based(creds: ApiCreds) -> impl Fn(&mut Request) {
                 body_data.extend_from_slice(bytes);
             }
         }
-        let mut hasher = Md5::new();
-        hasher.input(&body_data[..]);
-        let body_hash = hasher.result_str();
+        let body_hash = format!("{:x}", Md5::digest(&body_data[..]));
 
         // getting path + query
         let parsed_url = req.url();
@@ -52,17 +61,17 @@ pub fn sign_based(creds: ApiCreds) -> impl Fn(&mut Request) {
         sign_data.push('\n');
         sign_data.push_str(&body_hash[..]);
         sign_data.push('\n');
-        sign_data.push_str(req.headers()[header::CONTENT_TYPE].to_str().unwrap());
+        sign_data.push_str(header_value(req, header::CONTENT_TYPE));
         sign_data.push('\n');
-        sign_data.push_str(req.headers()[header::DATE].to_str().unwrap());
+        sign_data.push_str(header_value(req, header::DATE));
         sign_data.push('\n');
         sign_data.push_str(path.as_str());
 
-        let mut mac = Hmac::new(Sha1::new(), creds.secret_key.as_bytes());
-        mac.input(sign_data.as_bytes());
-        let mac_res = mac.result();
-        let signature = mac_res
-            .code()
+        let mut mac = Hmac::<Sha1>::new_varkey(creds.secret_key.as_bytes()).unwrap();
+        mac.update(sign_data.as_bytes());
+        let signature = mac
+            .finalize()
+            .into_bytes()
             .iter()
             .format_with("", |byte, f| f(&format_args!("{:02x}", byte)))
             .to_string();
@@ -76,10 +85,18 @@ pub fn sign_based(creds: ApiCreds) -> impl Fn(&mut Request) {
     }
 }
 
+// missing or non-ASCII headers are signed as empty strings
+fn header_value(req: &Request, name: header::HeaderName) -> &str {
+    req.headers()
+        .get(name)
+        .and_then(|val| val.to_str().ok())
+        .unwrap_or("")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{DateTime, NaiveDateTime, Utc};
+    use chrono::NaiveDateTime;
     use reqwest::{blocking::Request, Method, Url};
 
     fn setup_req() -> Request {
@@ -117,10 +134,11 @@ mod tests {
         let mut req = setup_req();
         let headers = req.headers_mut();
 
-        let now = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(1541423681, 0), Utc)
-            .format(DATE_HEADER_FORMAT)
-            .to_string()
-            .replace("UTC", "GMT");
+        let now = date_header(DateTime::<Utc>::from_utc(
+            NaiveDateTime::from_timestamp(1541423681, 0),
+            Utc,
+        ));
+        assert_eq!(now, "Mon, 05 Nov 2018 13:14:41 GMT");
 
         headers.insert("Date", now.parse().unwrap());
         headers.insert("Content-Type", "application/json".parse().unwrap());
@@ -132,4 +150,31 @@ mod tests {
             "Uploadcare testpk:3cbc4d2cf91f80c1ba162b926f8a975e8bec7995",
         );
     }
+
+    #[test]
+    fn test_sign_based_no_content_type() {
+        let creds = ApiCreds {
+            secret_key: "demoprivatekey".to_string(),
+            pub_key: "testpk".to_string(),
+        };
+
+        let mut req = setup_req();
+        req.headers_mut()
+            .insert("Date", "Mon, 05 Nov 2018 13:14:41 GMT".parse().unwrap());
+
+        sign_based(creds)(&mut req);
+
+        assert_eq!(
+            req.headers()[AUTH_HEADER_KEY],
+            "Uploadcare testpk:3d53a2b0be5891321bf1e8fec24d3c8b7099f76a",
+        );
+    }
+
+    #[test]
+    fn test_date_header_year() {
+        // ISO week based year differs from the calendar one here
+        let date = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(1609459200, 0), Utc);
+
+        assert_eq!(date_header(date), "Fri, 01 Jan 2021 00:00:00 GMT");
+    }
 }
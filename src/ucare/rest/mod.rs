@@ -1,16 +1,20 @@
 //! Provides a client for Uploadcare REST API
 
 use std::fmt::{self, Debug};
+use std::time::Duration;
 
 use chrono::Utc;
 use log::debug;
 use reqwest::{
-    blocking::{Body, Client as http_client, ClientBuilder, Request},
+    blocking::{Body, Client as http_client, ClientBuilder, Request, Response},
     header, Method, StatusCode, Url,
 };
 use serde::Deserialize;
 
-use super::{encode_url, ApiCreds, ErrValue, Error, IntoUrlQuery, CLIENT_VERSION};
+use super::trace::RequestTrace;
+use super::{
+    encode_url, proxy, request_id, ApiCreds, ErrValue, Error, IntoUrlQuery, CLIENT_VERSION,
+};
 
 mod auth;
 
@@ -18,12 +22,15 @@ const USER_AGENT_PREFIX: &str = "UploadcareRust";
 const API_URL: &str = "https://api.uploadcare.com";
 
 /// Available API versions for client to specify when making requests.
+/// Defaults to v0.6.
 #[derive(Debug)]
 pub enum ApiVersion {
     /// API version v0.5
     V05,
     /// API version v0.6 (prefered)
     V06,
+    /// API version v0.7
+    V07,
 }
 
 impl fmt::Display for ApiVersion {
@@ -31,25 +38,64 @@ impl fmt::Display for ApiVersion {
         match *self {
             ApiVersion::V05 => write!(f, "v0.5"),
             ApiVersion::V06 => write!(f, "v0.6"),
+            ApiVersion::V07 => write!(f, "v0.7"),
         }
     }
 }
 
+impl Default for ApiVersion {
+    fn default() -> Self {
+        ApiVersion::V06
+    }
+}
+
 /// Configuration for the client.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Config {
     /// Should be true if you want to use signature based authentication for the
     /// REST API calls.
     pub sign_based_auth: bool,
     /// REST API version to be used.
     pub api_version: ApiVersion,
+    /// How long idle connections are kept open for reuse. None keeps the
+    /// default of 90 seconds.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum number of idle connections kept open per host. None keeps the
+    /// default of no limit.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Use HTTP/2 for all requests without negotiating the protocol first.
+    /// Requests fail if the server does not speak HTTP/2, so make sure the API
+    /// endpoint you use supports it. Signature based auth is not affected since
+    /// it is calculated over the request before it is sent.
+    pub http2_prior_knowledge: bool,
+    /// Additional headers sent with every request, e.g. for auditing in your gateway.
+    /// `Authorization` and `Date` headers are ignored since auth depends on them.
+    pub default_headers: header::HeaderMap,
+    /// Proxy URL all requests are sent through, e.g. "http://proxy.local:3128".
+    /// "http", "https" and "socks5" schemes are supported, the latter requires
+    /// the `socks` feature. The URL is validated when the client is created.
+    pub proxy: Option<String>,
+    /// Username and password for the proxy basic auth
+    pub proxy_auth: Option<(String, String)>,
+}
+
+/// Authentication scheme used for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    /// Keys are sent as is with the `Uploadcare.Simple` scheme
+    Simple,
+    /// Requests are signed with the secret key
+    SignBased,
 }
 
 /// Client is responsible for preparing requests and making http calls.
 pub struct Client {
-    set_auth_header: Box<dyn Fn(&mut Request)>,
+    auth_mode: AuthMode,
+    simple_auth: Box<dyn Fn(&mut Request) + Send + Sync>,
+    sign_based_auth: Box<dyn Fn(&mut Request) + Send + Sync>,
 
     client: http_client,
+    api_url: String,
 }
 
 impl Debug for Client {
@@ -87,24 +133,73 @@ impl Client {
             .unwrap(),
         );
 
-        let http_client = ClientBuilder::new()
-            .default_headers(headers)
-            .build()
-            .unwrap();
+        for name in config.default_headers.keys() {
+            if name == header::AUTHORIZATION || name == header::DATE {
+                continue;
+            }
+            headers.remove(name);
+            for value in config.default_headers.get_all(name).iter() {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+
+        let mut builder = ClientBuilder::new().default_headers(headers);
+        if let Some(val) = config.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(val);
+        }
+        if let Some(val) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(val);
+        }
+        if let Some(ref url) = config.proxy {
+            builder = builder.proxy(proxy(url, config.proxy_auth.as_ref())?);
+        }
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        // responses are decompressed transparently, signature is calculated over
+        // the request so sign based auth is not affected
+        #[cfg(feature = "gzip")]
+        let builder = builder.gzip(true);
+        let http_client = builder.build().unwrap();
 
         let client = Client {
-            set_auth_header: if config.sign_based_auth {
-                Box::new(auth::sign_based(creds))
+            auth_mode: if config.sign_based_auth {
+                AuthMode::SignBased
             } else {
-                Box::new(auth::simple(creds))
+                AuthMode::Simple
             },
+            simple_auth: Box::new(auth::simple(creds.clone())),
+            sign_based_auth: Box::new(auth::sign_based(creds)),
 
             client: http_client,
+            api_url: API_URL.to_string(),
         };
 
         Ok(client)
     }
 
+    /// Initializes a client which sends all requests to the `api_url` instead of
+    /// the Uploadcare API, e.g. to a local mock server in the tests of your code.
+    /// Uses placeholder credentials and simple auth.
+    #[cfg(any(test, feature = "mock"))]
+    pub fn mock(api_url: &str) -> Self {
+        let creds = ApiCreds {
+            secret_key: "mock".to_string(),
+            pub_key: "mock".to_string(),
+        };
+        let mut client = Client::new(Config::default(), creds).unwrap();
+        client.api_url = api_url.trim_end_matches('/').to_string();
+        client
+    }
+
+    /// Makes a cheap authenticated request to the project endpoint.
+    ///
+    /// Use it at startup to validate the credentials, as `new` only checks
+    /// they are not empty, and to open a connection to the API beforehand.
+    pub fn ping(&self) -> Result<(), Error> {
+        crate::project::new_svc(self).info().map(|_| ())
+    }
+
     /// makes actual http request
     pub(crate) fn call<Q, D, R>(
         &self,
@@ -118,16 +213,89 @@ impl Client {
         Q: IntoUrlQuery,
         for<'de> R: Deserialize<'de>,
     {
-        let url = encode_url::<Q>(API_URL, path.as_str(), query)?;
+        let url = encode_url::<Q>(self.api_url.as_str(), path.as_str(), query)?;
         self.call_url::<D, R>(method, url, data)
     }
 
+    /// Makes a request to any REST API endpoint and returns raw JSON response.
+    ///
+    /// It is an escape hatch for endpoints and response fields not supported by
+    /// the library yet, the auth and error handling are the same as for other calls.
+    ///
+    /// ```rust,ignore
+    /// let info = rest_client.call_raw::<String, String>(
+    ///     reqwest::Method::GET,
+    ///     "/files/d6d34fa9-addd-472c-868d-2e5c105f9fcd/",
+    ///     None,
+    ///     None,
+    /// )?;
+    /// println!("{}", info["uuid"]);
+    /// ```
+    pub fn call_raw<Q, D>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<Q>,
+        data: Option<D>,
+    ) -> Result<serde_json::Value, Error>
+    where
+        Q: ToString,
+        D: Sized + Into<Body>,
+    {
+        self.call::<String, D, serde_json::Value>(
+            method,
+            path.to_string(),
+            query.map(|q| q.to_string()),
+            data,
+        )
+    }
+
+    /// Same as `call_raw` but authenticates the request with the passed scheme
+    /// instead of the configured one, e.g. to tell signing issues from invalid keys.
+    pub fn call_with_auth<Q, D>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<Q>,
+        data: Option<D>,
+        auth_mode: AuthMode,
+    ) -> Result<serde_json::Value, Error>
+    where
+        Q: ToString,
+        D: Sized + Into<Body>,
+    {
+        let url = encode_url::<String>(self.api_url.as_str(), path, query.map(|q| q.to_string()))?;
+        self.call_url_with_auth::<D, serde_json::Value>(method, url, data, auth_mode)
+    }
+
+    // fetches file contents, e.g. from the CDN, without the API auth
+    pub(crate) fn download(&self, url: Url) -> Result<Response, Error> {
+        debug!("downloading: {}", url);
+        let res = self.client.get(url).send()?;
+
+        Ok(res.error_for_status()?)
+    }
+
     pub(crate) fn call_url<D, R>(
         &self,
         method: Method,
         url: Url,
         data: Option<D>,
     ) -> Result<R, Error>
+    where
+        D: Sized + Into<Body>,
+        for<'de> R: Deserialize<'de>,
+    {
+        self.call_url_with_auth::<D, R>(method, url, data, self.auth_mode)
+    }
+
+    fn call_url_with_auth<D, R>(
+        &self,
+        method: Method,
+        url: Url,
+        data: Option<D>,
+        auth_mode: AuthMode,
+    ) -> Result<R, Error>
     where
         D: Sized + Into<Body>,
         for<'de> R: Deserialize<'de>,
@@ -135,13 +303,7 @@ impl Client {
         let mut req_builder = self
             .client
             .request(method, url)
-            .header(
-                header::DATE,
-                Utc::now()
-                    .format(auth::DATE_HEADER_FORMAT)
-                    .to_string()
-                    .replace("UTC", "GMT"),
-            )
+            .header(header::DATE, auth::date_header(Utc::now()))
             .header(
                 header::CONTENT_TYPE,
                 header::HeaderValue::from_static("application/json"),
@@ -151,34 +313,266 @@ impl Client {
         }
         let mut req = req_builder.build()?;
 
-        (*self.set_auth_header)(&mut req);
+        match auth_mode {
+            AuthMode::Simple => (*self.simple_auth)(&mut req),
+            AuthMode::SignBased => (*self.sign_based_auth)(&mut req),
+        }
 
         debug!("created new request: {:?}", req);
-        let res = self.client.execute(req)?;
-        debug!("received response: {:?}", res);
-
-        match res.status() {
-            StatusCode::BAD_REQUEST => Err(Error::with_value(ErrValue::BadRequest(
-                res.json::<Error>()?.detail(),
-            ))),
-            StatusCode::UNAUTHORIZED => Err(Error::with_value(ErrValue::Unauthorized(
-                res.json::<Error>()?.detail(),
-            ))),
-            StatusCode::NOT_ACCEPTABLE => Err(Error::with_value(ErrValue::NotAcceptable(
-                res.json::<Error>()?.detail(),
-            ))),
-            StatusCode::TOO_MANY_REQUESTS => {
-                let retry_after = res.headers()[header::RETRY_AFTER]
-                    .to_str()
-                    .unwrap()
-                    .parse::<i32>()
-                    .unwrap();
-                Err(Error::with_value(ErrValue::TooManyRequests(retry_after)))
-            }
-            StatusCode::OK | _ => {
-                let resp_data = res.json()?;
-                Ok(resp_data)
+        let trace = RequestTrace::start(&req);
+        let res = self.client.execute(req);
+        let elapsed = trace.finish(res.as_ref().ok().map(|res| res.status().as_u16()));
+        let res = res?;
+        let request_id = request_id(&res);
+        debug!(
+            "received response: {:?}, request id: {:?}, elapsed: {:?}",
+            res, request_id, elapsed
+        );
+
+        handle_response(res).map_err(|err| err.with_request_id(request_id))
+    }
+}
+
+// takes the error detail from the response body, falling back to the raw body
+// text if it is not JSON, so the detail is never lost. Compressed bodies are
+// decoded the same way for all statuses when the gzip feature is on
+fn error_detail(res: Response) -> Result<String, Error> {
+    let status = res.status();
+    let body = res.text_with_charset("utf-8")?;
+    if let Ok(err) = serde_json::from_str::<Error>(body.as_str()) {
+        return Ok(err.detail());
+    }
+    if body.trim().is_empty() {
+        return Ok(status.to_string());
+    }
+
+    Ok(body.trim().to_string())
+}
+
+fn handle_response<R>(res: Response) -> Result<R, Error>
+where
+    for<'de> R: Deserialize<'de>,
+{
+    match res.status() {
+        StatusCode::BAD_REQUEST => Err(Error::with_value(ErrValue::BadRequest(error_detail(res)?))),
+        StatusCode::UNAUTHORIZED => Err(Error::with_value(ErrValue::Unauthorized(error_detail(
+            res,
+        )?))),
+        StatusCode::NOT_FOUND => Err(Error::with_value(ErrValue::NotFound(error_detail(res)?))),
+        StatusCode::NOT_ACCEPTABLE => Err(Error::with_value(ErrValue::NotAcceptable(
+            error_detail(res)?,
+        ))),
+        StatusCode::CONFLICT => Err(Error::with_value(ErrValue::Conflict(error_detail(res)?))),
+        StatusCode::TOO_MANY_REQUESTS => {
+            let retry_after = res.headers()[header::RETRY_AFTER]
+                .to_str()
+                .unwrap()
+                .parse::<i32>()
+                .unwrap();
+            Err(Error::with_value(ErrValue::TooManyRequests(retry_after)))
+        }
+        status if status.is_server_error() => Err(Error::with_value(ErrValue::ServerError {
+            status: status.as_u16(),
+            body: res.text_with_charset("utf-8")?,
+        })),
+        StatusCode::OK | _ => {
+            let resp_data = res.json()?;
+            Ok(resp_data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::ucare::testing;
+
+    fn client() -> Client {
+        let config = Config {
+            sign_based_auth: false,
+            api_version: ApiVersion::V06,
+            ..Default::default()
+        };
+        let creds = ApiCreds {
+            secret_key: "testsk".to_string(),
+            pub_key: "testpk".to_string(),
+        };
+
+        Client::new(config, creds).unwrap()
+    }
+
+    #[test]
+    fn test_server_error() {
+        let url = testing::serve_once(
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 11\r\nConnection: close\r\n\r\nBad Gateway",
+        );
+
+        let res = client().call_url::<String, String>(Method::GET, url, None);
+
+        match res.unwrap_err().value() {
+            ErrValue::ServerError { status, body } => {
+                assert_eq!(status, 502);
+                assert_eq!(body, "Bad Gateway");
             }
+            val => panic!("unexpected error value: {}", val),
+        }
+    }
+
+    #[test]
+    fn test_conflict() {
+        let url = testing::serve_once(
+            "HTTP/1.1 409 Conflict\r\nContent-Length: 28\r\nConnection: close\r\n\r\n{\"detail\": \"Already exists\"}",
+        );
+
+        let res = client().call_url::<String, String>(Method::POST, url, None);
+
+        match res.unwrap_err().value() {
+            ErrValue::Conflict(msg) => assert_eq!(msg, "Already exists"),
+            val => panic!("unexpected error value: {}", val),
         }
     }
+
+    #[test]
+    fn test_not_found() {
+        let url = testing::serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 24\r\nConnection: close\r\n\r\n{\"detail\": \"Not found.\"}",
+        );
+
+        let res = client().call_url::<String, String>(Method::GET, url, None);
+
+        match res.unwrap_err().value() {
+            ErrValue::NotFound(msg) => assert_eq!(msg, "Not found."),
+            val => panic!("unexpected error value: {}", val),
+        }
+    }
+
+    #[test]
+    fn test_error_detail_not_json() {
+        let url = testing::serve_once(
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 17\r\nConnection: close\r\n\r\nmalformed request",
+        );
+
+        let res = client().call_url::<String, String>(Method::GET, url, None);
+
+        match res.unwrap_err().value() {
+            ErrValue::BadRequest(msg) => assert_eq!(msg, "malformed request"),
+            val => panic!("unexpected error value: {}", val),
+        }
+    }
+
+    #[test]
+    fn test_download() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndata",
+        );
+        let mut body = String::new();
+        client()
+            .download(url)
+            .unwrap()
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "data");
+
+        let url = testing::serve_once(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        assert!(client().download(url).is_err());
+    }
+
+    #[test]
+    fn test_mock() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 15\r\nConnection: close\r\n\r\n{\"uuid\": \"abc\"}",
+        );
+
+        let info = Client::mock(url.as_str())
+            .call_raw::<String, String>(Method::GET, "/files/abc/", None, None)
+            .unwrap();
+
+        assert_eq!(info["uuid"], "abc");
+    }
+
+    #[test]
+    fn test_call_with_auth() {
+        let (url, requests) = testing::capture_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+        );
+
+        Client::mock(url.as_str())
+            .call_with_auth::<String, String>(
+                Method::GET,
+                "/project/",
+                None,
+                None,
+                AuthMode::SignBased,
+            )
+            .unwrap();
+
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("authorization: uploadcare mock:"));
+    }
+
+    #[test]
+    fn test_client_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Client>();
+    }
+
+    #[test]
+    fn test_ping() {
+        let url = testing::serve_once(
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 39\r\nConnection: close\r\n\r\n{\"detail\": \"Incorrect authentication.\"}",
+        );
+
+        let err = Client::mock(url.as_str()).ping().unwrap_err();
+
+        assert!(err.to_string().contains("Incorrect authentication."));
+    }
+
+    #[test]
+    fn test_error_request_id() {
+        let url = testing::serve_once(
+            "HTTP/1.1 400 Bad Request\r\nX-Request-ID: 42abc\r\nContent-Length: 25\r\nConnection: close\r\n\r\n{\"detail\": \"Bad request\"}",
+        );
+
+        let err = client()
+            .call_url::<String, String>(Method::GET, url, None)
+            .unwrap_err();
+
+        assert_eq!(err.request_id(), Some("42abc"));
+        assert_eq!(
+            err.to_string(),
+            "Uploadcare: Bad request (request id: 42abc)"
+        );
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let mut default_headers = header::HeaderMap::new();
+        default_headers.insert("X-Tenant-ID", "tenant42".parse().unwrap());
+        default_headers.insert(header::AUTHORIZATION, "fake".parse().unwrap());
+        let config = Config {
+            default_headers,
+            ..Default::default()
+        };
+        let creds = ApiCreds {
+            secret_key: "testsk".to_string(),
+            pub_key: "testpk".to_string(),
+        };
+        let client = Client::new(config, creds).unwrap();
+
+        let (url, request) = testing::capture_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+        );
+        client
+            .call_url::<String, serde_json::Value>(Method::GET, url, None)
+            .unwrap();
+
+        let request = request.recv().unwrap().to_lowercase();
+        assert!(request.contains("x-tenant-id: tenant42"));
+        assert!(request.contains("authorization: uploadcare.simple testpk:testsk"));
+        assert!(!request.contains("fake"));
+    }
 }
@@ -0,0 +1,43 @@
+//! Helpers shared by the unit tests
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use reqwest::Url;
+
+/// Starts a local server which answers a single request with the raw
+/// http `response` and returns its url
+pub(crate) fn serve_once(response: &'static str) -> Url {
+    capture_request(response).0
+}
+
+/// Same as `serve_once`, but also returns a receiver of the raw request
+/// that the server got
+pub(crate) fn capture_request(response: &'static str) -> (Url, Receiver<String>) {
+    capture_requests(vec![response])
+}
+
+/// Same as `capture_request`, but answers as many requests as there are
+/// `responses`, one connection per request, so responses should close it
+pub(crate) fn capture_requests(responses: Vec<&'static str>) -> (Url, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+        }
+    });
+
+    (
+        Url::parse(format!("http://{}/", addr).as_str()).unwrap(),
+        rx,
+    )
+}
@@ -0,0 +1,60 @@
+//! Request spans emitted with the `tracing` feature, no-op otherwise
+
+use std::time::{Duration, Instant};
+
+use reqwest::blocking::Request;
+
+/// Traces a single API request: method, path, response status and elapsed time.
+/// The request is timed with or without the feature, so it can be logged.
+pub(crate) struct RequestTrace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    started: Instant,
+}
+
+impl RequestTrace {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn start(req: &Request) -> Self {
+        let span = tracing::debug_span!(
+            "uploadcare_request",
+            method = %req.method(),
+            path = req.url().path(),
+            status = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        );
+
+        RequestTrace {
+            span,
+            started: Instant::now(),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn start(_req: &Request) -> Self {
+        RequestTrace {
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the response status, None if the request failed without one.
+    /// Returns time elapsed since the start
+    #[cfg(feature = "tracing")]
+    pub(crate) fn finish(self, status: Option<u16>) -> Duration {
+        let elapsed = self.started.elapsed();
+        self.span.record("elapsed_ms", elapsed.as_millis() as u64);
+        match status {
+            Some(val) => {
+                self.span.record("status", val);
+                self.span.in_scope(|| tracing::debug!("request finished"));
+            }
+            None => self.span.in_scope(|| tracing::debug!("request failed")),
+        }
+
+        elapsed
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn finish(self, _status: Option<u16>) -> Duration {
+        self.started.elapsed()
+    }
+}
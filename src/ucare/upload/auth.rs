@@ -2,8 +2,9 @@
 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crypto::{hmac::Hmac, mac::Mac, sha2::Sha256};
+use hmac::{Hmac, Mac, NewMac};
 use itertools::Itertools;
+use sha2::Sha256;
 
 use crate::ucare::ApiCreds;
 
@@ -43,12 +44,11 @@ pub(crate) fn sign_based(creds: ApiCreds) -> impl Fn() -> Fields {
 }
 
 fn get_signature(secret_key: String, expire: u32) -> String {
-    let mut mac = Hmac::new(Sha256::new(), secret_key.as_bytes());
-    mac.input(expire.to_string().as_bytes());
-    let mac_res = mac.result();
+    let mut mac = Hmac::<Sha256>::new_varkey(secret_key.as_bytes()).unwrap();
+    mac.update(expire.to_string().as_bytes());
 
-    mac_res
-        .code()
+    mac.finalize()
+        .into_bytes()
         .iter()
         .format_with("", |byte, f| f(&format_args!("{:02x}", byte)))
         .to_string()
@@ -1,15 +1,17 @@
 //! Provides a client for Uploadcare Upload API
 
 use std::fmt::{self, Debug};
+use std::time::Duration;
 
 use log::debug;
 use reqwest::{
-    blocking::{multipart::Form, Client as http_client, ClientBuilder},
+    blocking::{multipart::Form, Body, Client as http_client, ClientBuilder, Response},
     header, Method, StatusCode, Url,
 };
 use serde::Deserialize;
 
-use super::{encode_url, ApiCreds, ErrValue, Error, IntoUrlQuery, Result};
+use super::trace::RequestTrace;
+use super::{encode_url, proxy, request_id, ApiCreds, ErrValue, Error, IntoUrlQuery, Result};
 
 pub(crate) mod auth;
 pub(crate) use auth::Fields;
@@ -17,22 +19,47 @@ pub(crate) use auth::Fields;
 const API_URL: &str = "https://upload.uploadcare.com";
 
 /// Configuration for the client.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Config {
     /// Should be true if you want to use signed uploads
     pub sign_based_upload: bool,
+    /// How long idle connections are kept open for reuse. None keeps the
+    /// default of 90 seconds.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum number of idle connections kept open per host. None keeps the
+    /// default of no limit.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How many times a multipart upload part is retried after a network or
+    /// server error. Defaults to no retries.
+    pub part_retries: u32,
+    /// Delay before the first part retry, doubled for each next one. None
+    /// keeps the default of 1 second.
+    pub part_retry_delay: Option<Duration>,
+    /// Proxy URL all requests are sent through, e.g. "http://proxy.local:3128".
+    /// "http", "https" and "socks5" schemes are supported, the latter requires
+    /// the `socks` feature. The URL is validated when the client is created.
+    pub proxy: Option<String>,
+    /// Username and password for the proxy basic auth
+    pub proxy_auth: Option<(String, String)>,
 }
 
 pub(crate) enum Payload {
     Form(Form),
-    Raw(Vec<u8>),
+    Raw {
+        data: Vec<u8>,
+        content_md5: Option<String>,
+    },
+    Stream(Body),
 }
 
 /// Client is responsible for preparing requests and making http calls.
 pub struct Client {
-    pub(crate) auth_fields: Box<dyn Fn() -> auth::Fields>,
+    pub(crate) auth_fields: Box<dyn Fn() -> auth::Fields + Send + Sync>,
+    pub(crate) part_retries: u32,
+    pub(crate) part_retry_delay: Duration,
 
     client: http_client,
+    api_url: String,
 }
 
 impl Debug for Client {
@@ -48,7 +75,17 @@ impl Client {
             return Err("Uploadcare: invalid api credentials provided".to_string());
         }
 
-        let http_client = ClientBuilder::new().build().unwrap();
+        let mut builder = ClientBuilder::new();
+        if let Some(val) = config.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(val);
+        }
+        if let Some(val) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(val);
+        }
+        if let Some(ref url) = config.proxy {
+            builder = builder.proxy(proxy(url, config.proxy_auth.as_ref())?);
+        }
+        let http_client = builder.build().unwrap();
 
         let client = Client {
             auth_fields: if config.sign_based_upload {
@@ -56,13 +93,32 @@ impl Client {
             } else {
                 Box::new(auth::simple(creds))
             },
+            part_retries: config.part_retries,
+            part_retry_delay: config
+                .part_retry_delay
+                .unwrap_or_else(|| Duration::from_secs(1)),
 
             client: http_client,
+            api_url: API_URL.to_string(),
         };
 
         Ok(client)
     }
 
+    /// Initializes a client which sends all requests to the `api_url` instead of
+    /// the Uploadcare API, e.g. to a local mock server in the tests of your code.
+    /// Uses placeholder credentials and simple auth.
+    #[cfg(any(test, feature = "mock"))]
+    pub fn mock(api_url: &str) -> Self {
+        let creds = ApiCreds {
+            secret_key: "mock".to_string(),
+            pub_key: "mock".to_string(),
+        };
+        let mut client = Client::new(Config::default(), creds).unwrap();
+        client.api_url = api_url.trim_end_matches('/').to_string();
+        client
+    }
+
     /// makes actual http request
     pub(crate) fn call<Q, R>(
         &self,
@@ -75,7 +131,7 @@ impl Client {
         Q: IntoUrlQuery,
         for<'de> R: Deserialize<'de> + Default,
     {
-        let url = encode_url::<Q>(API_URL, path.as_str(), query)?;
+        let url = encode_url::<Q>(self.api_url.as_str(), path.as_str(), query)?;
         self.call_url::<R>(method, url, data)
     }
 
@@ -94,44 +150,112 @@ impl Client {
                 Payload::Form(form) => {
                     req_builder = req_builder.multipart(form);
                 }
-                Payload::Raw(data) => {
+                Payload::Raw { data, content_md5 } => {
                     req_builder = req_builder
                         .body(data)
                         .header(header::CONTENT_TYPE, "application/octet-stream");
+                    if let Some(md5) = content_md5 {
+                        req_builder = req_builder.header("Content-MD5", md5);
+                    }
+                }
+                Payload::Stream(body) => {
+                    req_builder = req_builder
+                        .body(body)
+                        .header(header::CONTENT_TYPE, "application/octet-stream");
                 }
             }
         }
         let req = req_builder.build()?;
 
         debug!("created new request: {:?}", req);
-        let res = self.client.execute(req)?;
-        debug!("received response: {:?}", res);
-
-        match res.status() {
-            StatusCode::BAD_REQUEST => Err(Error::with_value(ErrValue::BadRequest(
-                res.text_with_charset("utf-8")?,
-            ))),
-            StatusCode::FORBIDDEN => Err(Error::with_value(ErrValue::Forbidden(
-                res.text_with_charset("utf-8")?,
-            ))),
-            StatusCode::NOT_FOUND => Err(Error::with_value(ErrValue::NotFound(
-                res.text_with_charset("utf-8")?,
-            ))),
-            StatusCode::PAYLOAD_TOO_LARGE => Err(Error::with_value(ErrValue::PayloadTooLarge(
-                res.text_with_charset("utf-8")?,
-            ))),
-            // picking 30 seconds because retry-after is not returned from the API
-            StatusCode::TOO_MANY_REQUESTS => Err(Error::with_value(ErrValue::TooManyRequests(30))),
-            StatusCode::OK | _ => match res.json() {
-                Ok(data) => Ok(data),
-                Err(err) => {
-                    if err.to_string().contains("EOF") {
-                        Ok(R::default())
-                    } else {
-                        Err(Error::from(err))
-                    }
+        let trace = RequestTrace::start(&req);
+        let res = self.client.execute(req);
+        let elapsed = trace.finish(res.as_ref().ok().map(|res| res.status().as_u16()));
+        let res = res?;
+        let request_id = request_id(&res);
+        debug!(
+            "received response: {:?}, request id: {:?}, elapsed: {:?}",
+            res, request_id, elapsed
+        );
+
+        handle_response(res).map_err(|err| err.with_request_id(request_id))
+    }
+}
+
+fn handle_response<R>(res: Response) -> Result<R, Error>
+where
+    for<'de> R: Deserialize<'de> + Default,
+{
+    match res.status() {
+        StatusCode::BAD_REQUEST => Err(Error::with_value(ErrValue::BadRequest(
+            res.text_with_charset("utf-8")?,
+        ))),
+        StatusCode::FORBIDDEN => Err(Error::with_value(ErrValue::Forbidden(
+            res.text_with_charset("utf-8")?,
+        ))),
+        StatusCode::NOT_FOUND => Err(Error::with_value(ErrValue::NotFound(
+            res.text_with_charset("utf-8")?,
+        ))),
+        StatusCode::PAYLOAD_TOO_LARGE => Err(Error::with_value(ErrValue::PayloadTooLarge(
+            res.text_with_charset("utf-8")?,
+        ))),
+        // picking 30 seconds because retry-after is not returned from the API
+        StatusCode::TOO_MANY_REQUESTS => Err(Error::with_value(ErrValue::TooManyRequests(30))),
+        status if status.is_server_error() => Err(Error::with_value(ErrValue::ServerError {
+            status: status.as_u16(),
+            body: res.text_with_charset("utf-8")?,
+        })),
+        StatusCode::OK | _ => match res.json() {
+            Ok(data) => Ok(data),
+            Err(err) => {
+                if err.to_string().contains("EOF") {
+                    Ok(R::default())
+                } else {
+                    Err(Error::from(err))
                 }
-            },
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucare::testing;
+
+    fn client() -> Client {
+        let config = Config {
+            sign_based_upload: false,
+            ..Default::default()
+        };
+        let creds = ApiCreds {
+            secret_key: "testsk".to_string(),
+            pub_key: "testpk".to_string(),
+        };
+
+        Client::new(config, creds).unwrap()
+    }
+
+    #[test]
+    fn test_client_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Client>();
+    }
+
+    #[test]
+    fn test_server_error() {
+        let url = testing::serve_once(
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 11\r\nConnection: close\r\n\r\nBad Gateway",
+        );
+
+        let res = client().call_url::<String>(Method::GET, url, None);
+
+        match res.unwrap_err().value() {
+            ErrValue::ServerError { status, body } => {
+                assert_eq!(status, 502);
+                assert_eq!(body, "Bad Gateway");
+            }
+            val => panic!("unexpected error value: {}", val),
         }
     }
 }
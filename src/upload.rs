@@ -16,39 +16,72 @@
 //! a distributed network.
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use reqwest::{blocking::multipart::Form, Method, Url};
+use md5::{Digest, Md5};
+use reqwest::{
+    blocking::{
+        multipart::{Form, Part},
+        Body,
+    },
+    Method, Url,
+};
 use serde::Deserialize;
 
-use crate::file::{ImageInfo, VideoInfo};
-use crate::ucare::{upload::Client, upload::Fields, upload::Payload, Result};
+use crate::cdn::{self, Operation};
+use crate::file::{ContentInfo, ImageInfo, VideoInfo};
+use crate::ucare::{
+    upload::Client, upload::Fields, upload::Payload, ClientRef, ErrValue, Error, Mime, Result,
+};
+
+pub use crate::ucare::ToStore;
 
 /// Service is used to make calls to file API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
 }
 
 /// creates new upload service instance
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+    }
+}
+
+/// creates new upload service instance which shares the client, so it can be stored
+/// along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+    }
 }
 
 impl Service<'_> {
     /// Uploads a file and return its unique id (uuid). Comply with the RFC7578 standard.
     /// Resulting HashMap holds filenames as keys and their ids are values.
+    ///
+    /// Unlike `from_url`, direct upload has no duplicates check, so retrying it
+    /// creates a new file each time. To make retries idempotent keep track of the
+    /// uploaded files by their `file_md5` checksum and skip the ones already
+    /// uploaded.
     pub fn file(&self, params: FileParams) -> Result<HashMap<String, String>> {
+        check_direct_upload_size(params.path.as_str())?;
+
+        let part = file_part(&params)?;
+        self.direct_upload(&params, part)
+    }
+
+    fn direct_upload(&self, params: &FileParams, part: Part) -> Result<HashMap<String, String>> {
         let mut form = Form::new()
-            .file(params.name.to_string(), params.path.to_string())?
-            .text(
-                "UPLOADCARE_STORE",
-                if let Some(val) = params.to_store {
-                    val
-                } else {
-                    ToStore::False
-                }
-                .to_string(),
-            );
+            .part(params.name.to_string(), part)
+            .text("UPLOADCARE_STORE", store_value(params.to_store));
         form = add_signature_expire(&(*self.client.auth_fields)(), form);
 
         self.client.call::<String, HashMap<String, String>>(
@@ -59,25 +92,113 @@ impl Service<'_> {
         )
     }
 
+    /// Uploads a single file and returns its unique id (uuid)
+    pub fn file_single(&self, params: FileParams) -> Result<String> {
+        single_id(self.file(params)?)
+    }
+
+    /// Same as `file_single`, but also returns base64 encoded MD5 checksum of the
+    /// uploaded contents, so it can be compared against the one computed elsewhere.
+    ///
+    /// The checksum is computed locally while the file is sent, it doesn't come
+    /// from Uploadcare. If `params.md5` is set, the file is verified against it
+    /// before sending and it is returned as is.
+    pub fn file_single_with_md5(&self, params: FileParams) -> Result<(String, String)> {
+        if let Some(ref md5) = params.md5 {
+            let md5 = md5.to_string();
+            return Ok((self.file_single(params)?, md5));
+        }
+
+        check_direct_upload_size(params.path.as_str())?;
+
+        let file = fs::File::open(params.path.as_str())?;
+        let len = file.metadata()?.len();
+        let md5 = Arc::new(Mutex::new(Md5::new()));
+        let reader = Md5Reader {
+            inner: file,
+            md5: md5.clone(),
+        };
+        let content_type = match params.content_type {
+            Some(ref val) => val.to_string(),
+            None => Path::new(params.path.as_str())
+                .extension()
+                .and_then(|ext| Mime::from_extension(&ext.to_string_lossy()))
+                .unwrap_or(Mime::OCTET_STREAM)
+                .to_string(),
+        };
+        let part = Part::reader_with_length(reader, len)
+            .file_name(params.name.to_string())
+            .mime_str(content_type.as_str())?;
+
+        let id = single_id(self.direct_upload(&params, part)?)?;
+        let digest = md5.lock().unwrap().clone().finalize();
+
+        Ok((id, base64::encode(digest)))
+    }
+
+    /// Uploads multiple files in a single request. Resulting HashMap holds filenames as
+    /// keys and their ids are values.
+    ///
+    /// Storing behaviour is set per request, so all files MUST have the same `to_store`
+    /// value. Combined size of the files MUST NOT exceed 100MB.
+    pub fn files(&self, files: Vec<FileParams>) -> Result<HashMap<String, String>> {
+        if files.is_empty() {
+            return Err(Error::with_value(ErrValue::Other(
+                "no files to upload".to_string(),
+            )));
+        }
+
+        let to_store = store_value(files[0].to_store);
+        let mut total_size: u64 = 0;
+        let mut form = Form::new();
+        for params in files.iter() {
+            if store_value(params.to_store) != to_store {
+                return Err(Error::with_value(ErrValue::Other(
+                    "all files uploaded in one request must have the same to_store value"
+                        .to_string(),
+                )));
+            }
+
+            total_size += fs::metadata(params.path.as_str())?.len();
+            if total_size > DIRECT_UPLOAD_MAX_SIZE {
+                return Err(Error::with_value(ErrValue::Other(format!(
+                    "combined size of files exceeds direct upload limit of {} bytes",
+                    DIRECT_UPLOAD_MAX_SIZE
+                ))));
+            }
+
+            form = form.part(params.name.to_string(), file_part(params)?);
+        }
+        form = form.text("UPLOADCARE_STORE", to_store);
+        form = add_signature_expire(&(*self.client.auth_fields)(), form);
+
+        self.client.call::<String, HashMap<String, String>>(
+            Method::POST,
+            "/base/".to_string(),
+            None,
+            Some(Payload::Form(form)),
+        )
+    }
+
     /// Uploads file by its public URL.
     pub fn from_url(&self, params: FromUrlParams) -> Result<FromUrlData> {
-        let mut form = Form::new().text("source_url", params.source_url).text(
-            "store",
-            if let Some(val) = params.to_store {
-                val
-            } else {
-                ToStore::False
-            }
-            .to_string(),
-        );
+        let mut form = Form::new()
+            .text("source_url", params.source_url)
+            .text("store", store_value(params.to_store));
         if let Some(val) = params.filename {
             form = form.text("filename", val);
         }
-        if let Some(val) = params.check_url_duplicates {
+        let check_url_duplicates = params.check_url_duplicates.map(|val| val.to_string());
+        if let Some(ref val) = check_url_duplicates {
             form = form.text("check_URL_duplicates", val.to_string());
         }
-        if let Some(val) = params.save_url_duplicates {
-            form = form.text("save_URL_duplicates", val.to_string());
+        // defaults to the value of check_url_duplicates
+        if let Some(val) = params
+            .save_url_duplicates
+            .map(|val| val.to_string())
+            .or(check_url_duplicates)
+        {
+            form = form.text("save_URL_duplicates", val);
         }
         form = add_signature_expire(&(*self.client.auth_fields)(), form);
 
@@ -89,6 +210,52 @@ impl Service<'_> {
         )
     }
 
+    /// Uploads file by its public URL and blocks until the upload is finished.
+    ///
+    /// Handles both `from_url` outcomes: returns the file info right away if it was
+    /// returned immediately, otherwise polls `from_url_status` every `poll_interval`
+    /// until the upload succeeds, fails or `timeout` elapses.
+    pub fn upload_from_url_blocking(
+        &self,
+        params: FromUrlParams,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<FileInfo> {
+        let token = match self.from_url(params)? {
+            FromUrlData::FileInfo(info) => return Ok(info),
+            FromUrlData::Token(data) => match data.token {
+                Some(token) => token,
+                None => {
+                    return Err(Error::with_value(ErrValue::Other(
+                        "from_url response holds neither token nor file info".to_string(),
+                    )))
+                }
+            },
+        };
+
+        let started = Instant::now();
+        loop {
+            match self.from_url_status(token.as_str())? {
+                FromUrlStatusData::Success(info) => return Ok(info),
+                FromUrlStatusData::Error { error } => {
+                    return Err(Error::with_value(ErrValue::Other(format!(
+                        "upload from url failed: {}",
+                        error
+                    ))))
+                }
+                _ => {}
+            }
+
+            if started.elapsed() >= timeout {
+                return Err(Error::with_value(ErrValue::Other(format!(
+                    "upload from url is not finished after {:?}, token: {}",
+                    timeout, token
+                ))));
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+
     /// Check the status of a file uploaded from URL.
     pub fn from_url_status(&self, token: &str) -> Result<FromUrlStatusData> {
         self.client.call::<String, FromUrlStatusData>(
@@ -110,6 +277,32 @@ impl Service<'_> {
         )
     }
 
+    /// Polls `file_info` every `poll_interval` until the file is ready to be used,
+    /// e.g. before requesting image transformations. Fails if the file is not
+    /// ready after `timeout`.
+    pub fn wait_ready(
+        &self,
+        file_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<FileInfo> {
+        let started = Instant::now();
+        loop {
+            let info = self.file_info(file_id)?;
+            if info.is_ready {
+                return Ok(info);
+            }
+
+            if started.elapsed() >= timeout {
+                return Err(Error::with_value(ErrValue::Other(format!(
+                    "file {} is not ready after {:?}",
+                    file_id, timeout
+                ))));
+            }
+            thread::sleep(poll_interval);
+        }
+    }
+
     /// Creates files group from a set of files by using their IDs with
     /// or without applied CDN media processing operations.
     ///
@@ -118,13 +311,51 @@ impl Service<'_> {
     ///      "d6d34fa9-addd-472c-868d-2e5c105f9fcd",
     ///      "b1026315-8116-4632-8364-607e64fca723/-/resize/x800/",
     ///   ]
-    pub fn create_group(&self, ids: &[&str]) -> Result<GroupInfo> {
+    ///
+    /// `GroupMember` values can be passed instead of strings to get the exact
+    /// format.
+    pub fn create_group<T: Display>(&self, ids: &[T]) -> Result<GroupInfo> {
+        let mut builder = GroupBuilder::new();
+        for id in ids.iter() {
+            builder = builder.raw(id.to_string().as_str());
+        }
+
+        self.create_group_with(builder)
+    }
+
+    /// Uploads files one by one and creates a group of them in the same order.
+    ///
+    /// If an upload fails, the group is not created and the error names the
+    /// failed file. Files uploaded before it are left as is and, unless stored,
+    /// are deleted within 24 hours as any other temporary file.
+    pub fn upload_files_as_group(&self, files: Vec<FileParams>) -> Result<GroupInfo> {
+        if files.is_empty() {
+            return Err(Error::with_value(ErrValue::Other(
+                "no files to upload".to_string(),
+            )));
+        }
+
+        let mut ids = Vec::with_capacity(files.len());
+        for (index, params) in files.into_iter().enumerate() {
+            let name = params.name.to_string();
+            let id = self.file_single(params).map_err(|err| {
+                err.with_context(format!(
+                    "upload of file {} ({}) failed, group is not created",
+                    index, name
+                ))
+            })?;
+            ids.push(id);
+        }
+
+        self.create_group(&ids)
+    }
+
+    /// Creates files group from the files added to the builder along with their
+    /// CDN operations.
+    pub fn create_group_with(&self, builder: GroupBuilder) -> Result<GroupInfo> {
         let mut form = Form::new();
-        for (pos, id) in ids.iter().enumerate() {
-            form = form.text(
-                ("files[".to_string() + pos.to_string().as_str() + "]").to_string(),
-                id.to_string(),
-            );
+        for (name, value) in builder.fields() {
+            form = form.text(name, value);
         }
         form = add_signature_expire(&(*self.client.auth_fields)(), form);
 
@@ -159,37 +390,197 @@ impl Service<'_> {
     /// instances thus quickly becoming available for further use.
     /// Note, there also exists a minimum file size to use with Multipart Uploads, 10MB.
     /// Trying to use Multipart upload with a smaller file will result in an error.
+    /// File parts MUST be of `part_size` bytes except for the last one, see
+    /// `MultipartData::part_ranges`.
     pub fn multipart_start(&self, params: MultipartParams) -> Result<MultipartData> {
+        if u64::from(params.size) < MULTIPART_MIN_SIZE {
+            return Err(Error::with_value(ErrValue::Other(format!(
+                "file size of {} bytes is below multipart upload minimum of {} bytes, use direct upload instead",
+                params.size, MULTIPART_MIN_SIZE
+            ))));
+        }
+        if let Some(val) = params.part_size {
+            if (val as usize) < MULTIPART_PART_SIZE {
+                return Err(Error::with_value(ErrValue::Other(format!(
+                    "part size must be at least {} bytes, got {}",
+                    MULTIPART_PART_SIZE, val
+                ))));
+            }
+        }
+
         let mut form = Form::new()
             .text("filename", params.filename)
-            .text(
-                "UPLOADCARE_STORE",
-                if let Some(val) = params.to_store {
-                    val
-                } else {
-                    ToStore::False
-                }
-                .to_string(),
-            )
+            .text("UPLOADCARE_STORE", store_value(params.to_store))
             .text("content_type", params.content_type)
             .text("size", params.size.to_string());
+        if let Some(val) = params.part_size {
+            form = form.text("part_size", val.to_string());
+        }
         form = add_signature_expire(&(*self.client.auth_fields)(), form);
 
-        self.client.call::<String, MultipartData>(
+        let mut data = self.client.call::<String, MultipartData>(
             Method::POST,
             format!("/multipart/start/"),
             None,
             Some(Payload::Form(form)),
-        )
+        )?;
+        data.part_size = params.part_size;
+
+        Ok(data)
     }
 
     /// The second phase is about uploading file parts to the provided URLs. Each uploaded part
     /// should be 5MB (5242880 bytes) in size except for the last one that can be smaller. You
     /// can upload file parts in parallel provided the byte order stays unchanged. Make sure to
     /// define Content-Type header for your data.
-    pub fn upload_part(&self, url: &str, data: Vec<u8>) -> Result<()> {
-        self.client
-            .call_url::<()>(Method::PUT, Url::parse(url)?, Some(Payload::Raw(data)))
+    ///
+    /// Borrowed data is copied into the request body. To send data shared in an
+    /// `Arc<[u8]>` or `bytes::Bytes` without the copy, pass it in `io::Cursor` to
+    /// `upload_part_from_reader`.
+    pub fn upload_part<D: Into<Vec<u8>>>(&self, url: &str, data: D) -> Result<()> {
+        self.client.call_url::<()>(
+            Method::PUT,
+            Url::parse(url)?,
+            Some(Payload::Raw {
+                data: data.into(),
+                content_md5: None,
+            }),
+        )
+    }
+
+    /// Same as `upload_part` but also sends the `Content-MD5` header so that a part
+    /// corrupted in transit is rejected by the storage.
+    pub fn upload_part_with_md5<D: Into<Vec<u8>>>(&self, url: &str, data: D) -> Result<()> {
+        let data = data.into();
+        let content_md5 = Some(md5_base64(&data));
+        self.client.call_url::<()>(
+            Method::PUT,
+            Url::parse(url)?,
+            Some(Payload::Raw { data, content_md5 }),
+        )
+    }
+
+    /// Same as `upload_part` but streams `len` bytes of the part from the reader
+    /// instead of taking them from memory.
+    pub fn upload_part_from_reader<R>(&self, url: &str, reader: R, len: u64) -> Result<()>
+    where
+        R: Read + Send + 'static,
+    {
+        self.client.call_url::<()>(
+            Method::PUT,
+            Url::parse(url)?,
+            Some(Payload::Stream(Body::sized(reader, len))),
+        )
+    }
+
+    /// Uploads file data with the multipart upload in one go: starts the upload,
+    /// uploads data parts one by one and completes the upload.
+    ///
+    /// Set `content_md5` to send MD5 checksum with each part, see `upload_part_with_md5`.
+    ///
+    /// Parts failed with network or server errors are retried according to the
+    /// `part_retries` and `part_retry_delay` client config, the error returned
+    /// after the last attempt names the part index.
+    ///
+    /// The data length MUST be equal to `params.size`, otherwise nothing is uploaded.
+    pub fn multipart_upload(
+        &self,
+        params: MultipartParams,
+        data: &[u8],
+        content_md5: bool,
+    ) -> Result<FileInfo> {
+        if data.len() as u64 != u64::from(params.size) {
+            return Err(Error::with_value(ErrValue::Other(format!(
+                "data length of {} bytes differs from the declared size of {} bytes",
+                data.len(),
+                params.size
+            ))));
+        }
+
+        let multipart_data = self.multipart_start(params)?;
+
+        let ranges = multipart_data.part_ranges(data.len() as u64);
+        for (index, (url, (start, end))) in multipart_data.parts.iter().zip(ranges).enumerate() {
+            let chunk = &data[start as usize..end as usize];
+            self.upload_part_retrying(index, url.as_str(), chunk, content_md5)?;
+        }
+
+        self.multipart_complete(multipart_data.uuid)
+    }
+
+    /// Same as `multipart_upload` but streams each part from the file at `path`,
+    /// so only a small buffer is kept in memory regardless of the file size.
+    /// Parts are sent without MD5 checksums, retries are the same.
+    ///
+    /// `params.size` is ignored, the size is taken from the file metadata instead.
+    pub fn multipart_upload_file(
+        &self,
+        mut params: MultipartParams,
+        path: &str,
+    ) -> Result<FileInfo> {
+        let size = fs::metadata(path)?.len();
+        params.size = u32::try_from(size).map_err(|_| {
+            Error::with_value(ErrValue::Other(format!(
+                "file size of {} bytes exceeds multipart upload maximum of {} bytes",
+                size,
+                u32::MAX
+            )))
+        })?;
+        let multipart_data = self.multipart_start(params)?;
+
+        let ranges = multipart_data.part_ranges(size);
+        for (index, (url, (start, end))) in multipart_data.parts.iter().zip(ranges).enumerate() {
+            self.retrying_part(index, || {
+                let mut file = fs::File::open(path)?;
+                file.seek(SeekFrom::Start(start))?;
+                self.upload_part_from_reader(url.as_str(), file.take(end - start), end - start)
+            })?;
+        }
+
+        self.multipart_complete(multipart_data.uuid)
+    }
+
+    fn upload_part_retrying(
+        &self,
+        index: usize,
+        url: &str,
+        chunk: &[u8],
+        content_md5: bool,
+    ) -> Result<()> {
+        self.retrying_part(index, || {
+            if content_md5 {
+                self.upload_part_with_md5(url, chunk)
+            } else {
+                self.upload_part(url, chunk)
+            }
+        })
+    }
+
+    // makes a part upload retrying transient failures with exponential backoff
+    fn retrying_part<F>(&self, index: usize, mut upload: F) -> Result<()>
+    where
+        F: FnMut() -> Result<()>,
+    {
+        let mut delay = self.client.part_retry_delay;
+        let mut attempt = 0;
+        loop {
+            let err = match upload() {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+
+            if !err.is_transient() || attempt >= self.client.part_retries {
+                return Err(err.with_context(format!(
+                    "upload of part {} failed after {} attempt(s)",
+                    index,
+                    attempt + 1
+                )));
+            }
+
+            attempt += 1;
+            thread::sleep(delay);
+            delay *= 2;
+        }
     }
 
     /// Complete multipart upload transaction when all file parts are uploaded
@@ -206,6 +597,49 @@ impl Service<'_> {
     }
 }
 
+/// Maximum size of a direct upload request, 100MB
+pub const DIRECT_UPLOAD_MAX_SIZE: u64 = 104_857_600;
+
+/// Minimum file size accepted by the multipart upload, 10MB
+pub const MULTIPART_MIN_SIZE: u64 = 10_485_760;
+
+/// Default size of each multipart upload part except for the last one, 5MB.
+/// It is also the minimum part size accepted by the API.
+pub const MULTIPART_PART_SIZE: usize = 5_242_880;
+
+/// Storing behaviour used when `to_store` is not set
+pub const DEFAULT_TO_STORE: ToStore = ToStore::False;
+
+/// Uploads a file with the upload client and stores it with the REST client,
+/// returning full file info.
+///
+/// Uploaded files are temporary until stored, so this covers the most common
+/// upload then store flow:
+///
+/// ```rust,ignore
+/// # use ucare::upload;
+///
+/// let params = upload::FileParams {
+///     path: "/path/to/file".to_string(),
+///     name: "filename".to_string(),
+///     to_store: None,
+///     content_type: None,
+///     md5: None,
+/// };
+/// let file_info = ucare::upload_and_store(&upload_client, &rest_client, params)?;
+/// assert!(file_info.datetime_stored.is_some());
+/// ```
+#[cfg(feature = "rest")]
+pub fn upload_and_store(
+    upload_client: &Client,
+    rest_client: &crate::ucare::rest::Client,
+    params: FileParams,
+) -> Result<crate::file::Info> {
+    let file_id = new_svc(upload_client).file_single(params)?;
+
+    crate::file::new_svc(rest_client).store(file_id.as_str())
+}
+
 /// Holds all possible params for the file upload
 #[derive(Default)]
 pub struct FileParams {
@@ -216,17 +650,29 @@ pub struct FileParams {
     /// respective description. If you want to upload larger files, please
     /// use multipart upload API methods.
     pub path: String,
-    /// Uploaded file name
+    /// Uploaded file name, also used as the original file name
     pub name: String,
-    /// File storing behaviour.
+    /// File storing behaviour: `True` stores the file, `False` keeps it temporary
+    /// so it is deleted in 24 hours unless stored, `Auto` follows the project
+    /// autostore setting. None is the same as `DEFAULT_TO_STORE`.
     pub to_store: Option<ToStore>,
+    /// File MIME-type, see `ucare::Mime` for the common ones. None infers
+    /// it from the path extension.
+    pub content_type: Option<String>,
+    /// Expected base64 encoded MD5 checksum of the file, see `file_md5`.
+    ///
+    /// The direct upload endpoint doesn't verify checksums, so the file contents
+    /// are checked against it right before sending and the upload fails on mismatch.
+    pub md5: Option<String>,
 }
 
 /// Parameters for upload from public URL link
 pub struct FromUrlParams {
     /// File URL, which should be a public HTTP or HTTPS link
     pub source_url: String,
-    /// File storing behaviour.
+    /// File storing behaviour: `True` stores the file, `False` keeps it temporary
+    /// so it is deleted in 24 hours unless stored, `Auto` follows the project
+    /// autostore setting. None is the same as `DEFAULT_TO_STORE`.
     pub to_store: Option<ToStore>,
     /// The name for a file uploaded from URL. If not defined, the filename is obtained from
     /// either response headers or a source URL
@@ -239,9 +685,9 @@ pub struct FromUrlParams {
     pub save_url_duplicates: Option<UrlDuplicates>,
 }
 
-/// Holds data returned by `from_url`
+/// Holds data returned by `from_url`, discriminated by its `type` field
 #[derive(Debug, Deserialize)]
-#[serde(untagged)]
+#[serde(tag = "type")]
 pub enum FromUrlData {
     /// Token
     #[serde(rename = "token")]
@@ -261,13 +707,18 @@ impl Default for FromUrlData {
 #[derive(Debug, Deserialize, Default)]
 pub struct FileToken {
     /// Value: "token"
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default = "token_type")]
     pub data_type: String,
     /// A token to identify a file for the upload status request
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token: Option<String>,
 }
 
+// the type field is consumed as the FromUrlData tag, so it is restored here
+fn token_type() -> String {
+    "token".to_string()
+}
+
 /// Holds the response returned by `from_url_status`
 #[derive(Debug, Deserialize)]
 #[serde(tag = "status")]
@@ -279,8 +730,10 @@ pub enum FromUrlStatusData {
     #[serde(rename = "progress")]
     Progress {
         /// Currently uploaded file size in bytes
+        #[serde(deserialize_with = "crate::ucare::de::number")]
         done: u32,
         /// Total file size in bytes
+        #[serde(deserialize_with = "crate::ucare::de::number")]
         total: u32,
     },
     /// File upload error
@@ -289,12 +742,12 @@ pub enum FromUrlStatusData {
         /// Error description
         error: String,
     },
-    /// Unknown
-    #[serde(rename = "unknown")]
-    Unknown,
     /// Waiting
     #[serde(rename = "waiting")]
     Waiting,
+    /// Unknown, also used for any status unknown to this version of the library
+    #[serde(rename = "unknown", other)]
+    Unknown,
 }
 
 impl Default for FromUrlStatusData {
@@ -309,12 +762,15 @@ pub struct FileInfo {
     /// True if file is stored
     pub is_stored: bool,
     /// Denotes currently uploaded file size in bytes
+    #[serde(deserialize_with = "crate::ucare::de::number")]
     pub done: u32,
     /// Same as uuid
     pub file_id: String,
     /// Total is same as size
+    #[serde(deserialize_with = "crate::ucare::de::number")]
     pub total: u32,
     /// File size in bytes
+    #[serde(deserialize_with = "crate::ucare::de::number")]
     pub size: u32,
     /// File UUID
     pub uuid: String,
@@ -337,6 +793,21 @@ pub struct FileInfo {
     pub s3_bucket: Option<String>,
     /// CDN media transformations applied to the file when its group was created
     pub default_effects: Option<String>,
+    /// Structured file content information, same as in the REST APIv0.7
+    pub content_info: Option<ContentInfo>,
+    /// File metadata set on upload
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl FileInfo {
+    /// Parses `default_effects` into CDN operations, returns an empty list if
+    /// there are none
+    pub fn default_effects_ops(&self) -> Vec<Operation> {
+        match self.default_effects {
+            Some(ref val) => cdn::parse_operations(val),
+            None => Vec::new(),
+        }
+    }
 }
 
 /// Group information
@@ -347,7 +818,7 @@ pub struct GroupInfo {
     /// When group was stored
     pub datetime_stored: Option<String>,
     /// Number of files in the group
-    #[serde(rename = "files_count")]
+    #[serde(rename = "files_count", deserialize_with = "crate::ucare::de::number")]
     pub file_count: u32,
     /// CDN URL of the group
     pub cdn_url: String,
@@ -359,6 +830,107 @@ pub struct GroupInfo {
     pub id: String,
 }
 
+impl GroupInfo {
+    /// Returns CDN URL of the group file by its zero-based index with the
+    /// operations applied: {cdn_url}nth/{index}/-/op1/
+    pub fn file_url(&self, index: usize, ops: &[Operation]) -> Result<String> {
+        if index >= self.file_count as usize {
+            return Err(Error::with_value(ErrValue::Other(format!(
+                "file index {} is out of range, group holds {} files",
+                index, self.file_count
+            ))));
+        }
+
+        let mut base = self.cdn_url.to_string();
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+        let url = cdn::with_operations(format!("{}nth/{}", base, index).as_str(), ops);
+        if ops.is_empty() {
+            return Ok(url + "/");
+        }
+
+        Ok(url)
+    }
+}
+
+/// Group file with CDN operations applied. Displays as the
+/// "uuid/-/operation/" path expected by `create_group`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupMember {
+    uuid: String,
+    ops: Vec<Operation>,
+}
+
+impl GroupMember {
+    /// Creates a group member from the file UUID and the operations to apply
+    pub fn new(uuid: &str, ops: Vec<Operation>) -> Self {
+        GroupMember {
+            uuid: uuid.to_string(),
+            ops,
+        }
+    }
+}
+
+impl From<&str> for GroupMember {
+    fn from(uuid: &str) -> Self {
+        GroupMember::new(uuid, Vec::new())
+    }
+}
+
+impl From<(&str, Vec<Operation>)> for GroupMember {
+    fn from((uuid, ops): (&str, Vec<Operation>)) -> Self {
+        GroupMember::new(uuid, ops)
+    }
+}
+
+impl Display for GroupMember {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", cdn::with_operations(&self.uuid, &self.ops))
+    }
+}
+
+/// Builds a list of group files with CDN operations applied
+#[derive(Debug, Default)]
+pub struct GroupBuilder {
+    files: Vec<String>,
+}
+
+impl GroupBuilder {
+    /// Creates an empty builder
+    pub fn new() -> Self {
+        GroupBuilder::default()
+    }
+
+    /// Adds a file by its UUID with the operations to apply
+    pub fn file(mut self, uuid: &str, ops: Vec<Operation>) -> Self {
+        self.files.push(cdn::with_operations(uuid, &ops));
+        self
+    }
+
+    /// Adds a group member
+    pub fn member(mut self, member: &GroupMember) -> Self {
+        self.files.push(member.to_string());
+        self
+    }
+
+    /// Adds a file by its UUID with operations already in the string form,
+    /// e.g. "b1026315-8116-4632-8364-607e64fca723/-/resize/x800/"
+    pub fn raw(mut self, path: &str) -> Self {
+        self.files.push(path.to_string());
+        self
+    }
+
+    /// Returns `files[N]` form fields as name-value pairs
+    pub fn fields(&self) -> Vec<(String, String)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(pos, file)| (format!("files[{}]", pos), file.to_string()))
+            .collect()
+    }
+}
+
 /// Params for starting multipart upload
 #[derive(Debug, Default)]
 pub struct MultipartParams {
@@ -366,10 +938,15 @@ pub struct MultipartParams {
     pub filename: String,
     /// Precise file size in bytes. Should not exceed your project file size cap.
     pub size: u32,
-    /// A file MIME-type
+    /// A file MIME-type, see `ucare::Mime` for the common ones
     pub content_type: String,
-    /// File storing behaviour.
+    /// File storing behaviour: `True` stores the file, `False` keeps it temporary
+    /// so it is deleted in 24 hours unless stored, `Auto` follows the project
+    /// autostore setting. None is the same as `DEFAULT_TO_STORE`.
     pub to_store: Option<ToStore>,
+    /// Size of each part except for the last one. None keeps the default of
+    /// `MULTIPART_PART_SIZE`, which is also the minimum the API accepts.
+    pub part_size: Option<u32>,
 }
 
 /// Response for starting multipart upload
@@ -379,9 +956,35 @@ pub struct MultipartData {
     pub parts: Vec<String>,
     /// Uploaded file UUID
     pub uuid: String,
+    // part size requested on start
+    #[serde(skip)]
+    part_size: Option<u32>,
+}
+
+impl MultipartData {
+    /// Returns `(start, end)` byte offsets, end is exclusive, of the file data to
+    /// upload to each of the parts URLs for the file of `total_size` bytes.
+    /// Every part is of the size requested on start, `MULTIPART_PART_SIZE` by
+    /// default, except for the last one.
+    pub fn part_ranges(&self, total_size: u64) -> Vec<(u64, u64)> {
+        let part_size = self
+            .part_size
+            .map_or(MULTIPART_PART_SIZE as u64, |val| val as u64);
+        part_ranges(self.parts.len(), part_size, total_size)
+    }
+}
+
+// splits total_size bytes into at most count ranges of part_size bytes
+fn part_ranges(count: usize, part_size: u64, total_size: u64) -> Vec<(u64, u64)> {
+    (0..count as u64)
+        .map(|pos| pos * part_size)
+        .filter(|start| *start < total_size)
+        .map(|start| (start, total_size.min(start + part_size)))
+        .collect()
 }
 
 /// Upload status
+#[derive(Debug, PartialEq)]
 pub enum UploadStatus {
     /// success
     Success,
@@ -409,51 +1012,119 @@ impl Display for UploadStatus {
     }
 }
 
-/// Sets the file storing behaviour
-pub enum ToStore {
+impl From<&FromUrlStatusData> for UploadStatus {
+    fn from(data: &FromUrlStatusData) -> Self {
+        match *data {
+            FromUrlStatusData::Success(_) => UploadStatus::Success,
+            FromUrlStatusData::Progress { .. } => UploadStatus::InProgress,
+            FromUrlStatusData::Error { .. } => UploadStatus::Error,
+            FromUrlStatusData::Waiting => UploadStatus::Waiting,
+            FromUrlStatusData::Unknown => UploadStatus::Unknown,
+        }
+    }
+}
+
+/// Used for FormUrlParams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlDuplicates {
     /// True
     True,
     /// False
     False,
-    /// Auto
-    Auto,
 }
 
-impl Display for ToStore {
+impl Display for UrlDuplicates {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let val = match *self {
-            ToStore::True => "1",
-            ToStore::False => "0",
-            ToStore::Auto => "auto",
+            UrlDuplicates::True => "1",
+            UrlDuplicates::False => "0",
         };
 
         write!(f, "{}", val)
     }
 }
 
-impl Debug for ToStore {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ToStore {}", self)
+fn check_direct_upload_size(path: &str) -> Result<()> {
+    let size = fs::metadata(path)?.len();
+    if size > DIRECT_UPLOAD_MAX_SIZE {
+        return Err(Error::with_value(ErrValue::Other(format!(
+            "file size of {} bytes exceeds direct upload limit of {} bytes, use multipart upload instead",
+            size, DIRECT_UPLOAD_MAX_SIZE
+        ))));
     }
+
+    Ok(())
 }
 
-/// Used for FormUrlParams
-pub enum UrlDuplicates {
-    /// True
-    True,
-    /// False
-    False,
+// returns the id of the only uploaded file
+fn single_id(uploaded: HashMap<String, String>) -> Result<String> {
+    if uploaded.len() != 1 {
+        return Err(Error::with_value(ErrValue::Other(format!(
+            "expected exactly one uploaded file, got {}",
+            uploaded.len()
+        ))));
+    }
+
+    Ok(uploaded.into_iter().next().unwrap().1)
 }
 
-impl Display for UrlDuplicates {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let val = match *self {
-            UrlDuplicates::True => "1",
-            UrlDuplicates::False => "0",
-        };
+// builds the form part of the file named after the params name rather than
+// its path
+fn file_part(params: &FileParams) -> Result<Part> {
+    if let Some(ref expected) = params.md5 {
+        let actual = file_md5(params.path.as_str())?;
+        if actual != *expected {
+            return Err(Error::with_value(ErrValue::Other(format!(
+                "MD5 checksum mismatch for {}: expected {}, got {}",
+                params.name, expected, actual
+            ))));
+        }
+    }
 
-        write!(f, "{}", val)
+    let mut part = Part::file(params.path.as_str())?.file_name(params.name.to_string());
+    if let Some(ref val) = params.content_type {
+        part = part.mime_str(val.as_str())?;
     }
+
+    Ok(part)
+}
+
+// returns the store form field value, "1", "0" or "auto"
+fn store_value(to_store: Option<ToStore>) -> String {
+    to_store.unwrap_or(DEFAULT_TO_STORE).to_string()
+}
+
+/// Returns base64 encoded MD5 checksum of the file contents, the same as sent
+/// in the `Content-MD5` header. Use it to identify files already uploaded.
+pub fn file_md5(path: &str) -> Result<String> {
+    let md5 = Arc::new(Mutex::new(Md5::new()));
+    let mut reader = Md5Reader {
+        inner: fs::File::open(path)?,
+        md5: md5.clone(),
+    };
+    io::copy(&mut reader, &mut io::sink())?;
+
+    let digest = md5.lock().unwrap().clone().finalize();
+    Ok(base64::encode(digest))
+}
+
+// computes MD5 checksum of the data read through it, the checksum is shared
+// so it can be read after the reader is moved into the request body
+struct Md5Reader<R> {
+    inner: R,
+    md5: Arc<Mutex<Md5>>,
+}
+
+impl<R: Read> Read for Md5Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.md5.lock().unwrap().update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+fn md5_base64(data: &[u8]) -> String {
+    base64::encode(Md5::digest(data))
 }
 
 fn add_signature_expire(auth_fields: &Fields, form: Form) -> Form {
@@ -469,3 +1140,347 @@ fn add_signature_expire(auth_fields: &Fields, form: Form) -> Form {
     )
     .text("expire", auth_fields.expire.as_ref().unwrap().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_file_url() {
+        let group = GroupInfo {
+            cdn_url: "https://ucarecdn.com/d52d7136-a2e5-4338-9f45-affbf83b857d~2/".to_string(),
+            file_count: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            group.file_url(1, &[]).unwrap(),
+            "https://ucarecdn.com/d52d7136-a2e5-4338-9f45-affbf83b857d~2/nth/1/",
+        );
+        assert_eq!(
+            group
+                .file_url(0, &[Operation::Format("png".to_string())])
+                .unwrap(),
+            "https://ucarecdn.com/d52d7136-a2e5-4338-9f45-affbf83b857d~2/nth/0/-/format/png/",
+        );
+        assert!(group.file_url(2, &[]).is_err());
+    }
+
+    #[test]
+    fn test_group_member() {
+        let uuid = "b1026315-8116-4632-8364-607e64fca723";
+
+        assert_eq!(GroupMember::from(uuid).to_string(), uuid);
+        assert_eq!(
+            GroupMember::from((
+                uuid,
+                vec![Operation::Resize {
+                    width: None,
+                    height: Some(800)
+                }]
+            ))
+            .to_string(),
+            "b1026315-8116-4632-8364-607e64fca723/-/resize/x800/",
+        );
+    }
+
+    #[test]
+    fn test_from_url_data() {
+        let data: FromUrlData = serde_json::from_str(
+            r#"{"type": "token", "token": "945ebb27-1fd6-46c6-a859-b9893712d650"}"#,
+        )
+        .unwrap();
+        match data {
+            FromUrlData::Token(token) => {
+                assert_eq!(token.data_type, "token");
+                assert_eq!(
+                    token.token.as_deref(),
+                    Some("945ebb27-1fd6-46c6-a859-b9893712d650")
+                );
+            }
+            val => panic!("unexpected data: {:?}", val),
+        }
+
+        let data: FromUrlData = serde_json::from_str(
+            r#"{
+                "type": "file_info",
+                "is_stored": true,
+                "done": 10,
+                "file_id": "945ebb27-1fd6-46c6-a859-b9893712d650",
+                "total": 10,
+                "size": 10,
+                "uuid": "945ebb27-1fd6-46c6-a859-b9893712d650",
+                "is_image": false,
+                "filename": "file.txt",
+                "is_ready": true,
+                "original_filename": "file.txt",
+                "mime_type": "text/plain"
+            }"#,
+        )
+        .unwrap();
+        match data {
+            FromUrlData::FileInfo(info) => {
+                assert_eq!(info.uuid, "945ebb27-1fd6-46c6-a859-b9893712d650")
+            }
+            val => panic!("unexpected data: {:?}", val),
+        }
+    }
+
+    #[test]
+    fn test_from_url_status_numeric_strings() {
+        let data: FromUrlStatusData =
+            serde_json::from_str(r#"{"status": "progress", "done": "5", "total": 10}"#).unwrap();
+
+        match data {
+            FromUrlStatusData::Progress { done, total } => {
+                assert_eq!(done, 5);
+                assert_eq!(total, 10);
+            }
+            val => panic!("unexpected data: {:?}", val),
+        }
+    }
+
+    #[test]
+    fn test_store_value() {
+        assert_eq!(store_value(Some(ToStore::True)), "1");
+        assert_eq!(store_value(Some(ToStore::False)), "0");
+        assert_eq!(store_value(Some(ToStore::Auto)), "auto");
+        assert_eq!(store_value(None), "0");
+    }
+
+    #[test]
+    fn test_md5_base64() {
+        assert_eq!(md5_base64(b""), "1B2M2Y8AsgTpgAmY7PhCfg==");
+        assert_eq!(
+            md5_base64(b"The quick brown fox jumps over the lazy dog"),
+            "nhB9nTcrtoJr2B01QqQZ1g==",
+        );
+    }
+
+    #[test]
+    fn test_part_ranges() {
+        let data = MultipartData {
+            parts: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            uuid: "".to_string(),
+            part_size: None,
+        };
+
+        assert_eq!(
+            data.part_ranges(10_905_778),
+            vec![
+                (0, 5_242_880),
+                (5_242_880, 10_485_760),
+                (10_485_760, 10_905_778)
+            ]
+        );
+        assert_eq!(data.part_ranges(5_242_880), vec![(0, 5_242_880)]);
+
+        assert_eq!(part_ranges(3, 4, 10), vec![(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(part_ranges(3, 4, 5), vec![(0, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn test_upload_files_as_group_failure() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let svc = new_svc(&client);
+        let files = vec![FileParams {
+            path: "./no_such_file.jpg".to_string(),
+            name: "missing".to_string(),
+            ..Default::default()
+        }];
+
+        let err = svc.upload_files_as_group(files).unwrap_err();
+
+        assert!(err
+            .detail()
+            .contains("upload of file 0 (missing) failed, group is not created"));
+        match err.value() {
+            ErrValue::InputOutput(_) => {}
+            _ => panic!("expected io error"),
+        }
+    }
+
+    #[test]
+    fn test_file_md5() {
+        let path = "./tests/test_image.jpg";
+        assert_eq!(
+            file_md5(path).unwrap(),
+            md5_base64(&fs::read(path).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_file_single_with_md5() {
+        use crate::ucare::testing;
+
+        let path = std::env::temp_dir().join("ucare_md5_test.txt");
+        fs::write(&path, b"The quick brown fox jumps over the lazy dog").unwrap();
+
+        let (url, requests) = testing::capture_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 56\r\nConnection: close\r\n\r\n{\"md5_test.txt\": \"b1026315-8116-4632-8364-607e64fca723\"}",
+        );
+        let client = Client::mock(url.as_str());
+        let params = FileParams {
+            path: path.to_string_lossy().to_string(),
+            name: "md5_test.txt".to_string(),
+            ..Default::default()
+        };
+
+        let (id, md5) = new_svc(&client).file_single_with_md5(params).unwrap();
+
+        assert_eq!(id, "b1026315-8116-4632-8364-607e64fca723");
+        assert_eq!(md5, "nhB9nTcrtoJr2B01QqQZ1g==");
+        assert!(requests.recv().unwrap().starts_with("POST /base/ "));
+    }
+
+    #[test]
+    fn test_file_md5_mismatch() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let svc = new_svc(&client);
+        let params = FileParams {
+            path: "./Cargo.toml".to_string(),
+            name: "Cargo.toml".to_string(),
+            md5: Some("1B2M2Y8AsgTpgAmY7PhCfg==".to_string()),
+            ..Default::default()
+        };
+
+        let err = svc.file(params).unwrap_err();
+
+        assert!(err
+            .detail()
+            .contains("MD5 checksum mismatch for Cargo.toml: expected 1B2M2Y8AsgTpgAmY7PhCfg=="));
+    }
+
+    #[test]
+    fn test_multipart_upload_size_mismatch() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let svc = new_svc(&client);
+        let params = || MultipartParams {
+            filename: "big.bin".to_string(),
+            size: 10_485_760,
+            content_type: "application/octet-stream".to_string(),
+            ..Default::default()
+        };
+
+        let err = svc
+            .multipart_upload(params(), &vec![0; 10_485_761], false)
+            .unwrap_err();
+        assert!(err.detail().contains(
+            "data length of 10485761 bytes differs from the declared size of 10485760 bytes"
+        ));
+
+        let err = svc
+            .multipart_upload(params(), &[0; 1024], false)
+            .unwrap_err();
+        assert!(err
+            .detail()
+            .contains("data length of 1024 bytes differs from the declared size"));
+    }
+
+    #[test]
+    fn test_multipart_upload_file_size() {
+        let path = std::env::temp_dir().join("ucare_multipart_size_test.bin");
+        fs::write(&path, [0; 1024]).unwrap();
+
+        let client = Client::mock("http://127.0.0.1:1");
+        let params = MultipartParams {
+            filename: "small.bin".to_string(),
+            size: 10_485_760,
+            content_type: "application/octet-stream".to_string(),
+            ..Default::default()
+        };
+
+        // the declared size is replaced with the actual one, which is too
+        // small for the multipart upload
+        let err = new_svc(&client)
+            .multipart_upload_file(params, &path.to_string_lossy())
+            .unwrap_err();
+        assert!(err
+            .detail()
+            .contains("file size of 1024 bytes is below multipart upload minimum"));
+    }
+
+    #[test]
+    fn test_multipart_start_min_size() {
+        let client = Client::mock("http://127.0.0.1:1");
+        let svc = new_svc(&client);
+        let params = MultipartParams {
+            filename: "small.bin".to_string(),
+            size: 1024,
+            content_type: "application/octet-stream".to_string(),
+            ..Default::default()
+        };
+
+        let err = svc.multipart_start(params).unwrap_err();
+
+        assert!(err
+            .detail()
+            .contains("below multipart upload minimum of 10485760 bytes"));
+    }
+
+    #[test]
+    fn test_upload_part_from_reader() {
+        use crate::ucare::testing;
+        use std::io::Cursor;
+        use std::sync::Arc;
+
+        let (url, requests) = testing::capture_request(
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        let client = Client::mock(url.as_str());
+        let svc = new_svc(&client);
+
+        let data: Arc<[u8]> = Arc::from(&b"data"[..]);
+        svc.upload_part_from_reader(url.as_str(), Cursor::new(data), 4)
+            .unwrap();
+
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.starts_with("put / "));
+        assert!(request.contains("content-length: 4"));
+        assert!(request.contains("content-type: application/octet-stream"));
+    }
+
+    #[test]
+    fn test_upload_part_retrying() {
+        use crate::ucare::{testing, upload::Config, ApiCreds};
+
+        let config = Config {
+            part_retries: 2,
+            part_retry_delay: Some(Duration::from_millis(1)),
+            ..Default::default()
+        };
+        let creds = ApiCreds {
+            secret_key: "testsk".to_string(),
+            pub_key: "testpk".to_string(),
+        };
+        let client = Client::new(config, creds).unwrap();
+        let svc = new_svc(&client);
+
+        // the server goes away after the first failure, so the retries fail
+        // with network errors
+        let url = testing::serve_once(
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        let err = svc
+            .upload_part_retrying(3, url.as_str(), b"data", false)
+            .unwrap_err();
+        assert!(err
+            .detail()
+            .contains("upload of part 3 failed after 3 attempt(s)"));
+
+        // client errors are not retried
+        let url = testing::serve_once(
+            "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        let err = svc
+            .upload_part_retrying(0, url.as_str(), b"data", false)
+            .unwrap_err();
+        assert!(err
+            .detail()
+            .contains("upload of part 0 failed after 1 attempt(s)"));
+        match err.value() {
+            ErrValue::Forbidden(_) => {}
+            _ => panic!("expected forbidden error"),
+        }
+    }
+}
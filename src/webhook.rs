@@ -1,29 +1,58 @@
 //! Holds all primitives and logic around the webhook resource.
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 
-use crate::ucare::{encode_json, rest::Client, Result};
+use crate::ucare::{encode_json, rest::Client, ClientRef, ErrValue, Error, Result};
 
 /// Service is used to make calls to webhook API.
 pub struct Service<'a> {
-    client: &'a Client,
+    client: ClientRef<'a, Client>,
 }
 
 /// creates an instance of the webhook service
 pub fn new_svc(client: &Client) -> Service {
-    Service { client }
+    Service {
+        client: ClientRef::Borrowed(client),
+    }
+}
+
+/// creates an instance of the webhook service which shares the client, so it can be stored
+/// along with the client in the app state
+pub fn new_shared_svc(client: Arc<Client>) -> Service<'static> {
+    Service {
+        client: ClientRef::Shared(client),
+    }
 }
 
 impl Service<'_> {
-    /// Returns a list of project webhooks
+    /// Returns a list of project webhooks.
+    ///
+    /// The endpoint is not paginated, all project webhooks come in a single
+    /// response array, so the list is never truncated.
     pub fn list(&self) -> Result<List> {
         self.client
             .call::<String, String, List>(Method::GET, format!("/webhooks/"), None, None)
     }
 
+    /// Acquires webhook information by its ID.
+    ///
+    /// The API has no endpoint for a single webhook, so it is looked up in the
+    /// project webhooks list.
+    pub fn get(&self, id: i32) -> Result<Info> {
+        match self.list()?.into_iter().find(|hook| hook.id == id) {
+            Some(hook) => Ok(hook),
+            None => Err(Error::with_value(ErrValue::NotFound(format!(
+                "webhook {} not found",
+                id
+            )))),
+        }
+    }
+
     /// Create and subscribe to webhook
     pub fn create(&self, mut params: CreateParams) -> Result<Info> {
         if params.is_active.is_none() {
@@ -51,6 +80,40 @@ impl Service<'_> {
         )
     }
 
+    /// Activates or deactivates webhook, leaving other attributes unchanged.
+    pub fn set_active(&self, id: i32, active: bool) -> Result<Info> {
+        self.update(UpdateParams {
+            id,
+            event: None,
+            target_url: None,
+            signing_secret: None,
+            is_active: Some(active),
+            version: None,
+        })
+    }
+
+    /// Reconciles project webhooks with the desired ones matched by target_url:
+    /// creates missing webhooks and updates the ones with a different event,
+    /// active state, version or signing secret. Webhooks not in the desired list
+    /// are deleted only if `delete_extra` is true.
+    pub fn sync(&self, desired: &[CreateParams], delete_extra: bool) -> Result<SyncReport> {
+        let existing = self.list()?;
+        let mut report = SyncReport::default();
+
+        for action in plan_sync(&existing, desired, delete_extra) {
+            match action {
+                SyncAction::Create(params) => report.created.push(self.create(params.clone())?),
+                SyncAction::Update(params) => report.updated.push(self.update(params)?),
+                SyncAction::Delete(hook) => {
+                    self.delete_by_id(hook.id)?;
+                    report.deleted.push(hook.clone());
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Unsubscribe and delete webhook.
     pub fn delete(&self, params: DeleteParams) -> Result<()> {
         let json = encode_json(&params)?;
@@ -69,13 +132,30 @@ impl Service<'_> {
 
         Ok(())
     }
+
+    /// Unsubscribe and delete webhook by its ID.
+    pub fn delete_by_id(&self, id: i32) -> Result<()> {
+        let res = self.client.call::<String, String, String>(
+            Method::DELETE,
+            format!("/webhooks/{}/", id),
+            None,
+            None,
+        );
+        if let Err(err) = res {
+            if !err.to_string().contains("EOF") {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
 }
 
-/// List of webhooks returned
+/// List of webhooks returned, the API responds with a plain array without pagination
 pub type List = Vec<Info>;
 
-/// Webhook information
-#[derive(Deserialize, Debug)]
+/// Webhook information. Webhooks are compared and hashed by their ID only
+#[derive(Deserialize, Debug, Clone)]
 pub struct Info {
     /// Webhook ID
     pub id: i32,
@@ -93,10 +173,26 @@ pub struct Info {
     pub project: i32,
     /// Whether it is active
     pub is_active: bool,
+    /// Version of the payload sent to the target_url, e.g. "0.7"
+    pub version: Option<String>,
+}
+
+impl PartialEq for Info {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Info {}
+
+impl Hash for Info {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
 }
 
 /// Params for creating webhook
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CreateParams {
     /// An event you subscribe to.
     pub event: Event,
@@ -104,20 +200,36 @@ pub struct CreateParams {
     /// unique for each project — event type combination.
     pub target_url: String,
     /// Payload can be signed with a secret to ensure that the request comes from the expected
-    /// sender. Leave None if you don't want to change it
+    /// sender. Leave None if you don't want to sign it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub signing_secret: Option<String>,
     /// Marks a subscription as either active or not, defaults to true, otherwise false.
     pub is_active: Option<bool>,
+    /// Version of the payload sent to the target_url, e.g. "0.7". Pin it to keep
+    /// the payload format when the project API version changes. Leave None to
+    /// use the default one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 /// Events to subscribe for
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum Event {
     /// Fires when file is uploaded
     #[serde(rename = "file.uploaded")]
     FileUploaded,
 }
 
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let val = match *self {
+            Event::FileUploaded => "file.uploaded",
+        };
+
+        write!(f, "{}", val)
+    }
+}
+
 /// Params for updating webhook
 #[derive(Debug, Serialize)]
 pub struct UpdateParams {
@@ -132,11 +244,92 @@ pub struct UpdateParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_url: Option<String>,
     /// Payload can be signed with a secret to ensure that the request comes from the expected
-    /// sender
+    /// sender. Leave it None if you don't want to change it.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub signing_secret: Option<String>,
     /// Marks a subscription as either active or not, leave it None if you don't want to change it.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_active: Option<bool>,
+    /// Version of the payload sent to the target_url, e.g. "0.7". Leave it None if
+    /// you don't want to change it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Holds webhooks changed by `sync`
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Webhooks created
+    pub created: Vec<Info>,
+    /// Webhooks updated, as returned after the update
+    pub updated: Vec<Info>,
+    /// Webhooks deleted
+    pub deleted: Vec<Info>,
+}
+
+// change to apply to the project webhooks, planned by `plan_sync`
+#[derive(Debug)]
+enum SyncAction<'a> {
+    Create(&'a CreateParams),
+    Update(UpdateParams),
+    Delete(&'a Info),
+}
+
+// decides what `sync` has to do for the existing webhooks to match the desired
+// ones, webhooks are matched by target_url
+fn plan_sync<'a>(
+    existing: &'a [Info],
+    desired: &'a [CreateParams],
+    delete_extra: bool,
+) -> Vec<SyncAction<'a>> {
+    let mut actions = Vec::new();
+
+    for params in desired.iter() {
+        let current = existing
+            .iter()
+            .find(|hook| hook.target_url == params.target_url);
+        let hook = match current {
+            None => {
+                actions.push(SyncAction::Create(params));
+                continue;
+            }
+            Some(hook) => hook,
+        };
+
+        let is_active = params.is_active.unwrap_or(true);
+        let event = params.event.to_string();
+        let secret_changed = match params.signing_secret {
+            Some(ref val) => *val != hook.signing_secret,
+            None => false,
+        };
+        let version_changed = params.version.is_some() && params.version != hook.version;
+        if hook.event == event && hook.is_active == is_active && !secret_changed && !version_changed
+        {
+            continue;
+        }
+
+        actions.push(SyncAction::Update(UpdateParams {
+            id: hook.id,
+            event: Some(params.event),
+            target_url: None,
+            signing_secret: params.signing_secret.clone(),
+            is_active: Some(is_active),
+            version: params.version.clone(),
+        }));
+    }
+
+    if delete_extra {
+        for hook in existing.iter() {
+            if !desired
+                .iter()
+                .any(|params| params.target_url == hook.target_url)
+            {
+                actions.push(SyncAction::Delete(hook));
+            }
+        }
+    }
+
+    actions
 }
 
 /// Params for deleting webhook
@@ -145,3 +338,157 @@ pub struct DeleteParams {
     /// Webhook will be found and deleted by its target_url
     pub target_url: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ucare::testing;
+
+    #[test]
+    fn test_get_not_found() {
+        let url = testing::serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n[]",
+        );
+        let client = Client::mock(url.as_str());
+
+        let err = new_svc(&client).get(42).unwrap_err();
+
+        assert!(err.to_string().contains("webhook 42 not found"));
+    }
+
+    #[test]
+    fn test_update_params_skip_none() {
+        let params = UpdateParams {
+            id: 1,
+            event: None,
+            target_url: None,
+            signing_secret: None,
+            is_active: Some(false),
+            version: None,
+        };
+        let json = String::from_utf8(encode_json(&params).unwrap()).unwrap();
+
+        assert_eq!(json, r#"{"id":1,"is_active":false}"#);
+    }
+
+    #[test]
+    fn test_create_params_skip_none() {
+        let params = CreateParams {
+            event: Event::FileUploaded,
+            target_url: "https://example.com/hook".to_string(),
+            signing_secret: None,
+            is_active: Some(true),
+            version: None,
+        };
+        let json = String::from_utf8(encode_json(&params).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"event":"file.uploaded","target_url":"https://example.com/hook","is_active":true}"#
+        );
+    }
+
+    fn hook(id: i32, target_url: &str) -> Info {
+        Info {
+            id,
+            created: "2020-01-01T00:00:00Z".to_string(),
+            updated: "2020-01-01T00:00:00Z".to_string(),
+            event: "file.uploaded".to_string(),
+            target_url: target_url.to_string(),
+            signing_secret: "secret".to_string(),
+            project: 1,
+            is_active: true,
+            version: Some("0.7".to_string()),
+        }
+    }
+
+    fn params(target_url: &str) -> CreateParams {
+        CreateParams {
+            event: Event::FileUploaded,
+            target_url: target_url.to_string(),
+            signing_secret: None,
+            is_active: None,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_sync_create_and_skip() {
+        let existing = vec![hook(1, "https://example.com/a")];
+        let desired = vec![
+            params("https://example.com/a"),
+            params("https://example.com/b"),
+        ];
+
+        let actions = plan_sync(&existing, &desired, false);
+
+        // is_active defaults to true, unset secret and version are left as is
+        assert_eq!(actions.len(), 1);
+        match actions[0] {
+            SyncAction::Create(params) => assert_eq!(params.target_url, "https://example.com/b"),
+            ref action => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn test_plan_sync_update() {
+        let mut inactive = hook(1, "https://example.com/a");
+        inactive.is_active = false;
+        let existing = vec![
+            inactive,
+            hook(2, "https://example.com/b"),
+            hook(3, "https://example.com/c"),
+            hook(4, "https://example.com/d"),
+        ];
+
+        let mut secret = params("https://example.com/b");
+        secret.signing_secret = Some("new secret".to_string());
+        let mut version = params("https://example.com/c");
+        version.version = Some("0.8".to_string());
+        let mut same = params("https://example.com/d");
+        same.signing_secret = Some("secret".to_string());
+        same.version = Some("0.7".to_string());
+        let desired = vec![params("https://example.com/a"), secret, version, same];
+
+        let actions = plan_sync(&existing, &desired, false);
+
+        let updated: Vec<(i32, Option<bool>, Option<String>, Option<String>)> = actions
+            .into_iter()
+            .map(|action| match action {
+                SyncAction::Update(params) => (
+                    params.id,
+                    params.is_active,
+                    params.signing_secret,
+                    params.version,
+                ),
+                action => panic!("unexpected action {:?}", action),
+            })
+            .collect();
+        assert_eq!(
+            updated,
+            vec![
+                (1, Some(true), None, None),
+                (2, Some(true), Some("new secret".to_string()), None),
+                (3, Some(true), None, Some("0.8".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_sync_delete_extra() {
+        let existing = vec![
+            hook(1, "https://example.com/a"),
+            hook(2, "https://example.com/b"),
+        ];
+        let desired = vec![params("https://example.com/a")];
+
+        assert!(plan_sync(&existing, &desired, false).is_empty());
+
+        let actions = plan_sync(&existing, &desired, true);
+        assert_eq!(actions.len(), 1);
+        match actions[0] {
+            SyncAction::Delete(hook) => assert_eq!(hook.id, 2),
+            ref action => panic!("unexpected action {:?}", action),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! Itegration tests for all of the REST API.
 //! Very primitive approach.
 
+use std::time::Duration;
+
 use rand::Rng;
 
 use ucare::{self, conversion, file, group, project, webhook};
@@ -11,6 +13,7 @@ fn rest_client_version(version: ucare::RestApiVersion) -> ucare::RestClient {
     let config = ucare::RestConfig {
         sign_based_auth: true,
         api_version: version,
+        ..Default::default()
     };
 
     ucare::RestClient::new(config, testenv::api_creds()).unwrap()
@@ -58,6 +61,14 @@ fn file() {
 
     assert!(files.len() >= limit as usize);
 
+    // file counts
+    let counts = file_svc.counts().unwrap();
+    assert!(counts.unstored >= limit);
+    assert_eq!(
+        counts.total,
+        counts.stored + counts.unstored + counts.removed
+    );
+
     // file info
     let first_file = files.pop().unwrap();
     let file = file_svc.info(&first_file.uuid).unwrap();
@@ -78,14 +89,8 @@ fn file() {
     );
 
     // file copy
-    let params = file::CopyParams {
-        source: file.uuid.to_string(),
-        store: None,
-        make_public: Some(file::MakePublic::True),
-        target: None,
-        pattern: None,
-    };
-    let copy_info = file_svc.copy(params).unwrap();
+    let params = file::CopyParams::builder(&file.uuid).build();
+    let copy_info = file_svc.local_copy(params).unwrap();
 
     assert_eq!(copy_info.result.original_filename, file.original_filename);
 
@@ -150,6 +155,7 @@ fn conversion() {
     let params = conversion::JobParams {
         paths: vec![list.results.unwrap().pop().unwrap().uuid + "/document/-/format/pdf/"],
         store: Some(conversion::ToStore::False),
+        save_in_group: None,
     };
     let job_result = conv_svc.document(params).unwrap();
     if let Some(mut jobs) = job_result.result {
@@ -185,9 +191,11 @@ fn webhook() {
         target_url: target_url.clone(),
         signing_secret: Some(sign_secret.to_string()),
         is_active: None,
+        version: Some("0.7".to_string()),
     };
     let hook = webhook_svc.create(create_params).unwrap();
     assert!(hook.is_active);
+    assert_eq!(hook.version.as_deref(), Some("0.7"));
     assert!(hook.created.len() > 0);
     assert!(hook.updated.len() > 0);
     assert_eq!(hook.signing_secret, sign_secret);
@@ -199,6 +207,7 @@ fn webhook() {
         target_url: None,
         signing_secret: Some(new_sign_secret.to_string()),
         is_active: Some(false),
+        version: None,
     };
     let hook = webhook_svc.update(update_params).unwrap();
     assert!(!hook.is_active);
@@ -210,6 +219,24 @@ fn webhook() {
     assert_eq!(res, ());
 }
 
+#[test]
+fn connection_reuse() {
+    let config = ucare::RestConfig {
+        sign_based_auth: true,
+        api_version: ucare::RestApiVersion::V06,
+        pool_idle_timeout: Some(Duration::from_secs(30)),
+        pool_max_idle_per_host: Some(1),
+        ..Default::default()
+    };
+    let client = ucare::RestClient::new(config, testenv::api_creds()).unwrap();
+    let project_svc = project::new_svc(&client);
+
+    for _ in 0..20 {
+        let info = project_svc.info().unwrap();
+        assert_ne!(info.pub_key, "".to_string());
+    }
+}
+
 #[test]
 fn project() {
     let client = rest_client_v06();
@@ -219,4 +246,7 @@ fn project() {
     let info = project_svc.info().unwrap();
     assert_ne!(info.name, "".to_string());
     assert_ne!(info.pub_key, "".to_string());
+
+    // ping
+    client.ping().unwrap();
 }
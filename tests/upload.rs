@@ -1,6 +1,6 @@
 use rand::Rng;
 use std::fs;
-use std::io::Read;
+use std::time::Duration;
 
 use ucare::{self, upload};
 
@@ -9,6 +9,7 @@ mod testenv;
 fn upload_client() -> ucare::UploadClient {
     let config = ucare::UploadConfig {
         sign_based_upload: true,
+        ..Default::default()
     };
 
     ucare::UploadClient::new(config, testenv::api_creds()).unwrap()
@@ -29,6 +30,8 @@ fn file_and_group() {
         path: "./tests/test_image.jpg".to_string(),
         name: filename.to_string(),
         to_store: Some(upload::ToStore::True),
+        content_type: Some(ucare::Mime::IMAGE_JPEG.to_string()),
+        md5: Some(upload::file_md5("./tests/test_image.jpg").unwrap()),
     };
     let short_file_info = upload_svc.file(params).unwrap();
 
@@ -39,6 +42,12 @@ fn file_and_group() {
 
     assert_eq!(file_info.file_id, file_id);
 
+    let file_info = upload_svc
+        .wait_ready(file_id, Duration::from_secs(1), Duration::from_secs(30))
+        .unwrap();
+
+    assert!(file_info.is_ready);
+
     // group
     let group_info = upload_svc
         .create_group(&[(file_id.to_string() + "/-/resize/x800/").as_str()])
@@ -46,6 +55,43 @@ fn file_and_group() {
     let group_info = upload_svc.group_info(group_info.id.as_str()).unwrap();
 
     assert_eq!(group_info.files.unwrap().len(), 1);
+
+    // single file
+    let params = upload::FileParams {
+        path: "./tests/test_image.jpg".to_string(),
+        name: filename + "_single",
+        to_store: None,
+        content_type: None,
+        md5: None,
+    };
+    let file_id = upload_svc.file_single(params).unwrap();
+
+    assert_ne!(file_id, "".to_string());
+}
+
+#[test]
+fn upload_and_store() {
+    let mut rng = rand::thread_rng();
+    let suff: u8 = rng.gen();
+
+    let upload_client = upload_client();
+    let rest_config = ucare::RestConfig {
+        sign_based_auth: true,
+        api_version: ucare::RestApiVersion::V06,
+        ..Default::default()
+    };
+    let rest_client = ucare::RestClient::new(rest_config, testenv::api_creds()).unwrap();
+
+    let params = upload::FileParams {
+        path: "./tests/test_image.jpg".to_string(),
+        name: "London_stored_".to_string() + suff.to_string().as_str(),
+        to_store: None,
+        content_type: None,
+        md5: None,
+    };
+    let file_info = ucare::upload_and_store(&upload_client, &rest_client, params).unwrap();
+
+    assert_ne!(file_info.datetime_stored, None);
 }
 
 #[test]
@@ -79,6 +125,27 @@ fn from_url() {
     };
 }
 
+#[test]
+fn from_url_blocking() {
+    let client = upload_client();
+    let upload_svc = upload::new_svc(&client);
+
+    let params = upload::FromUrlParams {
+        source_url:
+            "https://www.london.gov.uk/sites/default/files/renew-tower-hamlets-3814-2x1.jpg?v=87935"
+                .to_string(),
+        to_store: Some(upload::ToStore::False),
+        filename: Some("Great_London_blocking".to_string()),
+        check_url_duplicates: None,
+        save_url_duplicates: None,
+    };
+    let info = upload_svc
+        .upload_from_url_blocking(params, Duration::from_secs(1), Duration::from_secs(60))
+        .unwrap();
+
+    assert_ne!(info.uuid, "".to_string());
+}
+
 #[test]
 fn multipart() {
     let mut rng = rand::thread_rng();
@@ -87,19 +154,21 @@ fn multipart() {
     let client = upload_client();
     let upload_svc = upload::new_svc(&client);
 
-    let mut data = get_file_chunks("./tests/test_image_2.jpg").unwrap();
+    let data = fs::read("./tests/test_image_2.jpg").unwrap();
 
     let params = upload::MultipartParams {
         filename: "Porsche_".to_string() + suff.to_string().as_str(),
         size: 10_905_778,
         content_type: "image/jpeg".to_string(),
         to_store: None,
+        part_size: None,
     };
     let multipart_data = upload_svc.multipart_start(params).unwrap();
 
-    for url in multipart_data.parts.iter() {
+    let ranges = multipart_data.part_ranges(data.len() as u64);
+    for (url, (start, end)) in multipart_data.parts.iter().zip(ranges) {
         upload_svc
-            .upload_part(url.as_str(), data.remove(0))
+            .upload_part(url.as_str(), data[start as usize..end as usize].to_vec())
             .unwrap();
     }
 
@@ -110,26 +179,3 @@ fn multipart() {
     assert_eq!(file_info.uuid, multipart_data.uuid);
     assert!(file_info.size > 10_000_000);
 }
-
-fn get_file_chunks(path: &str) -> ucare::Result<Vec<Vec<u8>>> {
-    let mut file = fs::File::open(path)?;
-    let mut list_of_chunks = Vec::new();
-    let chunk_size: usize = 5_242_880; // 5MB
-
-    loop {
-        let mut chunk = Vec::with_capacity(chunk_size);
-        let n = file
-            .by_ref()
-            .take(chunk_size as u64)
-            .read_to_end(&mut chunk)?;
-        if n == 0 {
-            break;
-        }
-        list_of_chunks.push(chunk);
-        if n < chunk_size {
-            break;
-        }
-    }
-
-    Ok(list_of_chunks)
-}